@@ -2,15 +2,19 @@
 
 use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId};
+
+pub use common_io::{Capabilities, RoyaltyInfo, PROTOCOL_VERSION};
+
 pub type NftId = u64;
 
 pub struct ContractMetadata;
 
 pub const BLOCK_DURATION_IN_SECS: u32 = 3;
+pub const EXISTENTIAL_DEPOSIT: u128 = 10_000_000_000_000;
 
 impl Metadata for ContractMetadata {
     type Init = In<AutoNftInit>;
-    type Handle = InOut<AutoNftAction, AutoNftEvent>;
+    type Handle = InOut<AutoNftAction, Result<AutoNftEvent, AutoNftError>>;
     type Others = ();
     type Reply = ();
     type Signal = ();
@@ -33,10 +37,12 @@ pub struct Config {
     pub user_mint_limit: Option<u32>,
     // pub time_for_change: u32,
     pub time_to_action: Vec<(u32, Action)>,
-    pub transferable: bool,
+    pub royalty: u16,
+    pub payment_for_mint: u128,
+    pub transferable: Option<u64>,
     pub approvable: bool,
     pub burnable: bool,
-    pub sellable: bool,
+    pub sellable: Option<u64>,
     pub attendable: bool,
 }
 
@@ -61,6 +67,24 @@ pub enum AutoNftAction {
     GetTokenInfo {
         token_id: NftId,
     },
+    CanDelete,
+    GetTokenCombination {
+        token_id: NftId,
+    },
+    SetUser {
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfo {
+        token_id: NftId,
+        sale_price: u128,
+    },
+    IsApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+    },
+    GetCapabilities,
     Mint,
     Approve {
         to: ActorId,
@@ -69,6 +93,10 @@ pub enum AutoNftAction {
     RevokeApproval {
         token_id: NftId,
     },
+    ApproveForAll {
+        operator: ActorId,
+        approved: bool,
+    },
     Burn {
         token_id: NftId,
     },
@@ -94,9 +122,28 @@ pub enum AutoNftEvent {
         token_id: NftId,
     },
     TokenInfoReceived {
-        owner: ActorId,
+        token_owner: ActorId,
         approval: Option<ActorId>,
         sellable: bool,
+        collection_owner: ActorId,
+        royalty: u16,
+        user: Option<(ActorId, u64)>,
+        royalty_recipients: Vec<(ActorId, u16)>,
+    },
+    CanDelete(bool),
+    TokenCombinationReceived {
+        combination: Vec<u8>,
+    },
+    UserSet {
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfoReceived(RoyaltyInfo),
+    IsApprovedForAll(bool),
+    CapabilitiesReceived {
+        capabilities: Capabilities,
+        collection_owner: ActorId,
     },
     Initialized {
         config: Config,
@@ -117,6 +164,11 @@ pub enum AutoNftEvent {
     ApprovalRevoked {
         token_id: NftId,
     },
+    ApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+        approved: bool,
+    },
     Expanded {
         additional_links: Vec<(Vec<String>, u32)>,
     },
@@ -138,10 +190,12 @@ pub struct AutoNftState {
     pub tokens: Vec<(NftId, Nft)>,
     pub owners: Vec<(ActorId, Vec<NftId>)>,
     pub token_approvals: Vec<(NftId, ActorId)>,
+    pub operator_approvals: Vec<(ActorId, Vec<ActorId>)>,
     pub config: Config,
     pub nonce: NftId,
     pub img_links: Vec<(Vec<String>, u32)>,
     pub admins: Vec<ActorId>,
+    pub collection_owner: ActorId,
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
@@ -150,6 +204,7 @@ pub struct Nft {
     pub name: String,
     pub description: String,
     pub media_url: (u32, Vec<String>), // (number current image, vector of all image)
+    pub mint_time: u64,
 }
 
 #[derive(Encode, Decode, TypeInfo)]
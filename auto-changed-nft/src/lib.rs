@@ -13,11 +13,14 @@ struct NftContract {
     pub owners: HashMap<ActorId, HashSet<NftId>>,
     pub restriction_mint: HashMap<ActorId, u32>, // made in order to track the number of mint nft
     pub token_approvals: HashMap<NftId, ActorId>,
+    // operators approved by the owner to transfer all of the owner's tokens
+    pub operator_approvals: HashMap<ActorId, HashSet<ActorId>>,
     pub config: Config,
     pub nonce: NftId,
     pub img_links: Vec<(Vec<String>, u32)>,
     // pub reservations: HashMap<NftId, ReservationId>,
     pub admins: Vec<ActorId>,
+    pub collection_owner: ActorId,
 }
 static mut NFT_CONTRACT: Option<NftContract> = None;
 
@@ -31,6 +34,7 @@ impl NftContract {
         let msg_src = msg::source();
         self.check_tokens()?;
         self.check_mint_limit(&msg_src)?;
+        self.payment_for_mint()?;
 
         let rand_index = get_random_value(self.img_links.len() as u64);
         let mut img_link: Option<Vec<String>> = None;
@@ -69,6 +73,7 @@ impl NftContract {
                 name,
                 description: self.config.description.clone(),
                 media_url: (0, img_link.clone().unwrap()),
+                mint_time: exec::block_timestamp(),
             },
         );
         self.restriction_mint
@@ -182,10 +187,49 @@ impl NftContract {
         Ok(AutoNftEvent::Burnt { token_id })
     }
 
+    fn approve_for_all(
+        &mut self,
+        operator: &ActorId,
+        approved: bool,
+    ) -> Result<AutoNftEvent, AutoNftError> {
+        let owner = msg::source();
+        if owner == *operator {
+            return Err(AutoNftError("Self approval is not allowed".to_owned()));
+        }
+        if approved {
+            self.operator_approvals
+                .entry(owner)
+                .or_default()
+                .insert(*operator);
+        } else if let Some(operators) = self.operator_approvals.get_mut(&owner) {
+            operators.remove(operator);
+            if operators.is_empty() {
+                self.operator_approvals.remove(&owner);
+            }
+        }
+
+        Ok(AutoNftEvent::ApprovedForAll {
+            owner,
+            operator: *operator,
+            approved,
+        })
+    }
+
+    fn is_approved_for_all(&self, owner: &ActorId, operator: &ActorId) -> bool {
+        self.operator_approvals
+            .get(owner)
+            .map_or(false, |operators| operators.contains(operator))
+    }
+
     fn get_token_info(&self, token_id: NftId) -> Result<AutoNftEvent, AutoNftError> {
         let nft = self.tokens.get(&token_id);
-        let owner = if let Some(nft) = nft {
-            nft.owner
+        let (token_owner, can_sell) = if let Some(nft) = nft {
+            let can_sell = if let Some(time) = self.config.sellable {
+                exec::block_timestamp() >= nft.mint_time + time
+            } else {
+                false
+            };
+            (nft.owner, can_sell)
         } else {
             return Err(AutoNftError(
                 "NonFungibleToken: token does not exist".to_owned(),
@@ -194,11 +238,46 @@ impl NftContract {
         let approval = self.token_approvals.get(&token_id).copied();
 
         Ok(AutoNftEvent::TokenInfoReceived {
-            owner,
+            token_owner,
             approval,
-            sellable: self.config.sellable,
+            sellable: can_sell,
+            collection_owner: self.collection_owner,
+            royalty: self.config.royalty,
+            user: None,
+            royalty_recipients: vec![],
+        })
+    }
+    fn royalty_info(
+        &self,
+        token_id: NftId,
+        sale_price: u128,
+    ) -> Result<AutoNftEvent, AutoNftError> {
+        if !self.tokens.contains_key(&token_id) {
+            return Err(AutoNftError(
+                "NonFungibleToken: token does not exist".to_owned(),
+            ));
+        }
+        Ok(AutoNftEvent::RoyaltyInfoReceived(RoyaltyInfo::new(
+            self.collection_owner,
+            self.config.royalty,
+            &[],
+            sale_price,
+        )))
+    }
+    fn get_capabilities(&self) -> Result<AutoNftEvent, AutoNftError> {
+        Ok(AutoNftEvent::CapabilitiesReceived {
+            capabilities: Capabilities {
+                version: PROTOCOL_VERSION,
+                operator_approvals: true,
+                rentals: false,
+                token_combinations: false,
+            },
+            collection_owner: self.collection_owner,
         })
     }
+    fn can_delete(&self) -> Result<AutoNftEvent, AutoNftError> {
+        Ok(AutoNftEvent::CanDelete(self.tokens.is_empty()))
+    }
     fn expand(
         &mut self,
         additional_links: Vec<(Vec<String>, u32)>,
@@ -227,6 +306,16 @@ impl NftContract {
             ));
         }
 
+        // made 10_000 so you can enter hundredths of a percent.
+        if config.royalty > 10_000 {
+            return Err(AutoNftError(
+                "Royalty percent must be less than 100%".to_owned(),
+            ));
+        }
+        if config.transferable.is_none() && config.sellable.is_some() {
+            return Err(AutoNftError("Tokens must be transferable".to_owned()));
+        }
+
         if let Some(limit) = config.user_mint_limit {
             if limit == 0 {
                 return Err(AutoNftError(
@@ -316,6 +405,18 @@ impl NftContract {
         }
         Ok(())
     }
+    fn payment_for_mint(&self) -> Result<(), AutoNftError> {
+        if self.config.payment_for_mint != 0 {
+            if msg::value() != self.config.payment_for_mint {
+                return Err(AutoNftError("Incorrectly entered mint fee.".to_owned()));
+            }
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(self.collection_owner, "", 0, self.config.payment_for_mint)
+                .expect("Error in sending value");
+        }
+
+        Ok(())
+    }
     fn can_approve(&self, token_id: &NftId) -> Result<(), AutoNftError> {
         if !self.config.approvable {
             return Err(AutoNftError("Nft is not approvable.".to_owned()));
@@ -354,10 +455,6 @@ impl NftContract {
         to: &ActorId,
         token_id: &NftId,
     ) -> Result<(), AutoNftError> {
-        if !self.config.transferable {
-            return Err(AutoNftError("Nft is not transferable.".to_owned()));
-        }
-
         let nft = self.tokens.get(token_id);
 
         if let Some(nft) = nft {
@@ -366,9 +463,19 @@ impl NftContract {
                 return Err(AutoNftError("NonFungibleToken: access denied".to_owned()));
             }
             let msg_src = msg::source();
-            if owner != msg_src {
+            if owner != msg_src && !self.is_approved_for_all(&owner, &msg_src) {
                 self.check_approve(&msg_src, token_id)?;
             }
+            if let Some(time) = self.config.transferable {
+                if exec::block_timestamp() < nft.mint_time + time {
+                    return Err(AutoNftError(
+                        "NonFungibleToken: transfer will be available after the deadline"
+                            .to_owned(),
+                    ));
+                }
+            } else {
+                return Err(AutoNftError("Nft is not transferable.".to_owned()));
+            }
         } else {
             return Err(AutoNftError(
                 "NonFungibleToken: token does not exist".to_owned(),
@@ -422,25 +529,44 @@ extern "C" fn init() {
     if img_links.iter().any(|&(_, value)| value == 0) {
         panic!("Limit of copies value is equal to 0");
     }
+    if config.payment_for_mint > 0 && config.payment_for_mint < EXISTENTIAL_DEPOSIT {
+        panic!(
+            "{}",
+            format!(
+                "The payment for mint must be greater than existential deposit ({})",
+                EXISTENTIAL_DEPOSIT
+            )
+        );
+    }
+    // made 10_000 so hundredths of a percent could be entered.
+    if config.royalty > 10_000 {
+        panic!("Royalty percent must be less than 100%");
+    }
+    // can't be made sellable but not transferable.
+    if config.transferable.is_none() && config.sellable.is_some() {
+        panic!("Tokens must be transferable");
+    }
 
     unsafe {
         NFT_CONTRACT = Some(NftContract {
             tokens: HashMap::new(),
             owners: HashMap::new(),
             token_approvals: HashMap::new(),
+            operator_approvals: HashMap::new(),
             restriction_mint: HashMap::new(),
             config: config.clone(),
             nonce: 0,
             img_links,
             // reservations: HashMap::new(),
             admins: vec![owner],
+            collection_owner: owner,
         })
     };
     msg::send(
         owner,
-        AutoNftEvent::Initialized {
+        Ok::<AutoNftEvent, AutoNftError>(AutoNftEvent::Initialized {
             config: config.clone(),
-        },
+        }),
         0,
     )
     .expect("Error during send to owner `AutoNftEvent::Initialized`");
@@ -470,11 +596,29 @@ extern "C" fn handle() {
         }
         AutoNftAction::Approve { to, token_id } => nft_contract.approve(&to, token_id),
         AutoNftAction::RevokeApproval { token_id } => nft_contract.revoke_approve(token_id),
+        AutoNftAction::ApproveForAll { operator, approved } => {
+            nft_contract.approve_for_all(&operator, approved)
+        }
+        AutoNftAction::IsApprovedForAll { owner, operator } => Ok(AutoNftEvent::IsApprovedForAll(
+            nft_contract.is_approved_for_all(&owner, &operator),
+        )),
         AutoNftAction::Burn { token_id } => nft_contract.burn(token_id),
         AutoNftAction::Expand { additional_links } => nft_contract.expand(additional_links),
         AutoNftAction::ChangeConfig { config } => nft_contract.change_config(config),
         AutoNftAction::GetTokenInfo { token_id } => nft_contract.get_token_info(token_id),
         AutoNftAction::ChangeImg { token_id } => nft_contract.change_image(token_id),
+        AutoNftAction::CanDelete => nft_contract.can_delete(),
+        AutoNftAction::GetCapabilities => nft_contract.get_capabilities(),
+        AutoNftAction::RoyaltyInfo {
+            token_id,
+            sale_price,
+        } => nft_contract.royalty_info(token_id, sale_price),
+        AutoNftAction::GetTokenCombination { .. } => Err(AutoNftError(
+            "This collection does not support token combinations".to_owned(),
+        )),
+        AutoNftAction::SetUser { .. } => Err(AutoNftError(
+            "This collection does not support token users".to_owned(),
+        )),
     };
 
     msg::reply(result, 0)
@@ -513,10 +657,12 @@ impl From<NftContract> for AutoNftState {
             tokens,
             owners,
             token_approvals,
+            operator_approvals,
             config,
             nonce,
             img_links,
             admins,
+            collection_owner,
             ..
         } = value;
 
@@ -532,15 +678,21 @@ impl From<NftContract> for AutoNftState {
             .iter()
             .map(|(nft_id, actor_id)| (*nft_id, *actor_id))
             .collect();
+        let operator_approvals = operator_approvals
+            .into_iter()
+            .map(|(owner, operators)| (owner, operators.into_iter().collect()))
+            .collect();
 
         Self {
             tokens,
             owners,
             token_approvals,
+            operator_approvals,
             config,
             nonce,
             img_links,
             admins,
+            collection_owner,
         }
     }
 }
@@ -0,0 +1,87 @@
+#![no_std]
+
+//! Message types that are shared by all nft collections,
+//! so that other programs can work with any collection in the same way
+
+use gstd::{prelude::*, ActorId};
+
+/// Reply to the `RoyaltyInfo { token_id, sale_price }` action of a collection
+/// * receivers - accounts that receive the royalty and the amount for each of them
+/// * amount - total royalty on the sale
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct RoyaltyInfo {
+    pub receivers: Vec<(ActorId, u128)>,
+    pub amount: u128,
+}
+
+impl RoyaltyInfo {
+    /// Splits the royalty between the recipients by their shares (in basis points of the price),
+    /// without recipients the whole royalty goes to the collection owner
+    pub fn new(
+        collection_owner: ActorId,
+        royalty: u16,
+        royalty_recipients: &[(ActorId, u16)],
+        sale_price: u128,
+    ) -> Self {
+        let receivers: Vec<(ActorId, u128)> = if royalty_recipients.is_empty() {
+            vec![(collection_owner, royalty)]
+        } else {
+            royalty_recipients.to_vec()
+        }
+        .into_iter()
+        // sale_price * share / 10_000
+        .map(|(receiver, share)| (receiver, sale_price * share as u128 / 10_000))
+        .filter(|(_, amount)| *amount > 0)
+        .collect();
+        let amount = receivers.iter().map(|(_, amount)| amount).sum();
+
+        Self { receivers, amount }
+    }
+}
+
+/// The royalty can be split between several recipients,
+/// then their shares (in basis points of the price) must add up to the royalty
+pub fn royalty_recipients_are_valid(royalty: u16, royalty_recipients: &[(ActorId, u16)]) -> bool {
+    royalty_recipients.is_empty()
+        || royalty_recipients
+            .iter()
+            .map(|(_, share)| *share as u32)
+            .sum::<u32>()
+            == royalty as u32
+}
+
+/// The shares of the royalty recipients in proportion to the new royalty,
+/// without recipients the whole royalty goes to the collection owner
+pub fn scale_royalty_recipients(
+    royalty: u16,
+    royalty_recipients: &[(ActorId, u16)],
+    new_royalty: u16,
+) -> Vec<(ActorId, u16)> {
+    if royalty == 0 {
+        return Vec::new();
+    }
+    royalty_recipients
+        .iter()
+        .map(|(recipient, share)| {
+            let share = *share as u32 * new_royalty as u32 / royalty as u32;
+            (*recipient, share as u16)
+        })
+        .collect()
+}
+
+/// Version of the protocol that the collections share with the marketplace,
+/// the init payload of every collection starts with the `ActorId` of the collection owner
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Reply to the `GetCapabilities` action of a collection, sent together with the collection owner
+/// * version - version of the collection protocol
+/// * operator_approvals - the owner can approve an operator for all of the owner's tokens
+/// * rentals - the owner can give the rights to use the token for a time
+/// * token_combinations - the tokens are combined from layers, so that offers can be made for a trait
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct Capabilities {
+    pub version: u16,
+    pub operator_approvals: bool,
+    pub rentals: bool,
+    pub token_combinations: bool,
+}
@@ -3,6 +3,11 @@
 use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId};
 
+pub use common_io::{
+    royalty_recipients_are_valid, scale_royalty_recipients, Capabilities, RoyaltyInfo,
+    PROTOCOL_VERSION,
+};
+
 pub type NftId = u64;
 pub const EXISTENTIAL_DEPOSIT: u128 = 10_000_000_000_000;
 
@@ -35,6 +40,7 @@ pub struct Config {
     pub tokens_limit: Option<u64>,
     pub additional_links: Option<AdditionalLinks>,
     pub royalty: u16,
+    pub royalty_recipients: Vec<(ActorId, u16)>,
     pub payment_for_mint: u128,
     pub transferable: Option<u64>,
     pub sellable: Option<u64>,
@@ -64,6 +70,23 @@ pub enum ComposableNftAction {
         token_id: NftId,
     },
     CanDelete,
+    GetTokenCombination {
+        token_id: NftId,
+    },
+    SetUser {
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfo {
+        token_id: NftId,
+        sale_price: u128,
+    },
+    IsApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+    },
+    GetCapabilities,
     Mint {
         combination: Vec<u8>,
     },
@@ -74,9 +97,17 @@ pub enum ComposableNftAction {
     RevokeApproval {
         token_id: NftId,
     },
+    ApproveForAll {
+        operator: ActorId,
+        approved: bool,
+    },
     ChangeConfig {
         config: Config,
     },
+    SetTokenRoyalty {
+        token_id: NftId,
+        royalty: Option<u16>,
+    },
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
@@ -92,8 +123,24 @@ pub enum ComposableNftEvent {
         sellable: bool,
         collection_owner: ActorId,
         royalty: u16,
+        user: Option<(ActorId, u64)>,
+        royalty_recipients: Vec<(ActorId, u16)>,
     },
     CanDelete(bool),
+    TokenCombinationReceived {
+        combination: Vec<u8>,
+    },
+    UserSet {
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfoReceived(RoyaltyInfo),
+    IsApprovedForAll(bool),
+    CapabilitiesReceived {
+        capabilities: Capabilities,
+        collection_owner: ActorId,
+    },
     Initialized {
         config: Config,
     },
@@ -108,9 +155,18 @@ pub enum ComposableNftEvent {
     ApprovalRevoked {
         token_id: NftId,
     },
+    ApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+        approved: bool,
+    },
     ConfigChanged {
         config: Config,
     },
+    TokenRoyaltySet {
+        token_id: NftId,
+        royalty: Option<u16>,
+    },
 }
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
 pub struct ComposableNftError(pub String);
@@ -120,12 +176,14 @@ pub struct ComposableNftState {
     pub tokens: Vec<(NftId, Nft)>,
     pub owners: Vec<(ActorId, Vec<NftId>)>,
     pub token_approvals: Vec<(NftId, ActorId)>,
+    pub operator_approvals: Vec<(ActorId, Vec<ActorId>)>,
     pub config: Config,
     pub nonce: NftId,
     pub img_links: Vec<Vec<String>>,
     pub collection_owner: ActorId,
     pub restriction_mint: Vec<(ActorId, u32)>,
     pub number_combination: u64,
+    pub token_royalties: Vec<(NftId, u16)>,
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
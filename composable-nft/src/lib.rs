@@ -13,10 +13,15 @@ struct ComposableNftContract {
     pub owners: HashMap<ActorId, HashSet<NftId>>,
     pub restriction_mint: HashMap<ActorId, u32>,
     pub token_approvals: HashMap<NftId, ActorId>,
+    // operators approved by the owner to transfer all of the owner's tokens
+    pub operator_approvals: HashMap<ActorId, HashSet<ActorId>>,
     pub config: Config,
     pub nonce: NftId,
     pub img_links: Vec<Vec<String>>,
     pub combinations: HashSet<Vec<u8>>,
+    pub token_combinations: HashMap<NftId, Vec<u8>>,
+    // royalty of the tokens that differs from the royalty of the collection
+    pub token_royalties: HashMap<NftId, u16>,
     pub collection_owner: ActorId,
     pub number_combination: u64,
 }
@@ -70,6 +75,7 @@ impl ComposableNftContract {
         let mut current_combination = Vec::with_capacity(combination.len());
         current_combination.extend_from_slice(&combination);
         self.combinations.insert(current_combination);
+        self.token_combinations.insert(token_id, combination);
 
         Ok(ComposableNftEvent::Minted { token_id, nft_data })
     }
@@ -154,6 +160,42 @@ impl ComposableNftContract {
         Ok(ComposableNftEvent::ApprovalRevoked { token_id })
     }
 
+    fn approve_for_all(
+        &mut self,
+        operator: &ActorId,
+        approved: bool,
+    ) -> Result<ComposableNftEvent, ComposableNftError> {
+        let owner = msg::source();
+        if owner == *operator {
+            return Err(ComposableNftError(
+                "Self approval is not allowed".to_owned(),
+            ));
+        }
+        if approved {
+            self.operator_approvals
+                .entry(owner)
+                .or_default()
+                .insert(*operator);
+        } else if let Some(operators) = self.operator_approvals.get_mut(&owner) {
+            operators.remove(operator);
+            if operators.is_empty() {
+                self.operator_approvals.remove(&owner);
+            }
+        }
+
+        Ok(ComposableNftEvent::ApprovedForAll {
+            owner,
+            operator: *operator,
+            approved,
+        })
+    }
+
+    fn is_approved_for_all(&self, owner: &ActorId, operator: &ActorId) -> bool {
+        self.operator_approvals
+            .get(owner)
+            .map_or(false, |operators| operators.contains(operator))
+    }
+
     fn change_config(&mut self, config: Config) -> Result<ComposableNftEvent, ComposableNftError> {
         self.check_collection_owner()?;
 
@@ -168,6 +210,11 @@ impl ComposableNftContract {
                 "Royalty percent must be less than 100%".to_owned(),
             ));
         }
+        if !royalty_recipients_are_valid(config.royalty, &config.royalty_recipients) {
+            return Err(ComposableNftError(
+                "The shares of the royalty recipients must add up to the royalty".to_owned(),
+            ));
+        }
         if config.transferable.is_none() && config.sellable.is_some() {
             return Err(ComposableNftError("Tokens must be transferable".to_owned()));
         }
@@ -198,15 +245,102 @@ impl ComposableNftContract {
             ));
         };
         let approval = self.token_approvals.get(&token_id).copied();
+        let (royalty, royalty_recipients) = self.token_royalty(token_id);
 
         Ok(ComposableNftEvent::TokenInfoReceived {
             token_owner,
             approval,
             sellable: can_sell,
             collection_owner: self.collection_owner,
-            royalty: self.config.royalty,
+            royalty,
+            user: None,
+            royalty_recipients,
         })
     }
+    fn set_token_royalty(
+        &mut self,
+        token_id: NftId,
+        royalty: Option<u16>,
+    ) -> Result<ComposableNftEvent, ComposableNftError> {
+        self.check_collection_owner()?;
+        let token_owner = if let Some(nft) = self.tokens.get(&token_id) {
+            nft.owner
+        } else {
+            return Err(ComposableNftError(
+                "ComposableNft: token does not exist".to_owned(),
+            ));
+        };
+        // made 10_000 so you can enter hundredths of a percent.
+        if royalty.map_or(false, |royalty| royalty > 10_000) {
+            return Err(ComposableNftError(
+                "Royalty percent must be less than 100%".to_owned(),
+            ));
+        }
+        // the holder of the token has agreed to the current royalty,
+        // so it can't be raised on the token that the collection owner has given away
+        let new_royalty = royalty.unwrap_or(self.config.royalty);
+        if token_owner != self.collection_owner && new_royalty > self.token_royalty(token_id).0 {
+            return Err(ComposableNftError(
+                "The royalty of the token held by another account can only be lowered".to_owned(),
+            ));
+        }
+        if let Some(royalty) = royalty {
+            self.token_royalties.insert(token_id, royalty);
+        } else {
+            self.token_royalties.remove(&token_id);
+        }
+
+        Ok(ComposableNftEvent::TokenRoyaltySet { token_id, royalty })
+    }
+    /// The royalty of the token and the shares of the royalty recipients,
+    /// the shares are scaled to the royalty of the token if it is overridden
+    fn token_royalty(&self, token_id: NftId) -> (u16, Vec<(ActorId, u16)>) {
+        match self.token_royalties.get(&token_id) {
+            Some(&royalty) => (
+                royalty,
+                scale_royalty_recipients(
+                    self.config.royalty,
+                    &self.config.royalty_recipients,
+                    royalty,
+                ),
+            ),
+            None => (self.config.royalty, self.config.royalty_recipients.clone()),
+        }
+    }
+    fn royalty_info(
+        &self,
+        token_id: NftId,
+        sale_price: u128,
+    ) -> Result<ComposableNftEvent, ComposableNftError> {
+        if !self.tokens.contains_key(&token_id) {
+            return Err(ComposableNftError(
+                "ComposableNft: token does not exist".to_owned(),
+            ));
+        }
+        let (royalty, royalty_recipients) = self.token_royalty(token_id);
+
+        Ok(ComposableNftEvent::RoyaltyInfoReceived(RoyaltyInfo::new(
+            self.collection_owner,
+            royalty,
+            &royalty_recipients,
+            sale_price,
+        )))
+    }
+
+    fn get_token_combination(
+        &self,
+        token_id: NftId,
+    ) -> Result<ComposableNftEvent, ComposableNftError> {
+        if let Some(combination) = self.token_combinations.get(&token_id) {
+            Ok(ComposableNftEvent::TokenCombinationReceived {
+                combination: combination.clone(),
+            })
+        } else {
+            Err(ComposableNftError(
+                "ComposableNft: token does not exist".to_owned(),
+            ))
+        }
+    }
 
     fn payment_for_mint(&self) -> Result<(), ComposableNftError> {
         if self.config.payment_for_mint != 0 {
@@ -223,6 +357,17 @@ impl ComposableNftContract {
         Ok(())
     }
 
+    fn get_capabilities(&self) -> Result<ComposableNftEvent, ComposableNftError> {
+        Ok(ComposableNftEvent::CapabilitiesReceived {
+            capabilities: Capabilities {
+                version: PROTOCOL_VERSION,
+                operator_approvals: true,
+                rentals: false,
+                token_combinations: true,
+            },
+            collection_owner: self.collection_owner,
+        })
+    }
     fn can_delete(&self) -> Result<ComposableNftEvent, ComposableNftError> {
         Ok(ComposableNftEvent::CanDelete(self.tokens.is_empty()))
     }
@@ -327,7 +472,7 @@ impl ComposableNftContract {
                 ));
             }
             let msg_src = msg::source();
-            if owner != msg_src {
+            if owner != msg_src && !self.is_approved_for_all(&owner, &msg_src) {
                 self.check_approve(&msg_src, token_id)?;
             }
             if let Some(time) = self.config.transferable {
@@ -386,6 +531,10 @@ extern "C" fn init() {
     if config.royalty > 10_000 {
         panic!("Royalty percent must be less than 100%");
     }
+    assert!(
+        royalty_recipients_are_valid(config.royalty, &config.royalty_recipients),
+        "The shares of the royalty recipients must add up to the royalty"
+    );
     assert!(
         config.tokens_limit.map(|limit| limit > 0).unwrap_or(true),
         "The tokens limit must be greater than zero"
@@ -409,11 +558,14 @@ extern "C" fn init() {
             tokens: HashMap::new(),
             owners: HashMap::new(),
             token_approvals: HashMap::new(),
+            operator_approvals: HashMap::new(),
             restriction_mint: HashMap::new(),
             config: config.clone(),
             nonce: 0,
             img_links,
             combinations: HashSet::new(),
+            token_combinations: HashMap::new(),
+            token_royalties: HashMap::new(),
             collection_owner: owner,
             number_combination,
         })
@@ -450,9 +602,31 @@ extern "C" fn handle() {
         }
         ComposableNftAction::Approve { to, token_id } => nft_contract.approve(&to, token_id),
         ComposableNftAction::RevokeApproval { token_id } => nft_contract.revoke_approve(token_id),
+        ComposableNftAction::ApproveForAll { operator, approved } => {
+            nft_contract.approve_for_all(&operator, approved)
+        }
+        ComposableNftAction::IsApprovedForAll { owner, operator } => {
+            Ok(ComposableNftEvent::IsApprovedForAll(
+                nft_contract.is_approved_for_all(&owner, &operator),
+            ))
+        }
         ComposableNftAction::ChangeConfig { config } => nft_contract.change_config(config),
         ComposableNftAction::GetTokenInfo { token_id } => nft_contract.get_token_info(token_id),
         ComposableNftAction::CanDelete => nft_contract.can_delete(),
+        ComposableNftAction::GetCapabilities => nft_contract.get_capabilities(),
+        ComposableNftAction::SetUser { .. } => Err(ComposableNftError(
+            "This collection does not support token users".to_owned(),
+        )),
+        ComposableNftAction::GetTokenCombination { token_id } => {
+            nft_contract.get_token_combination(token_id)
+        }
+        ComposableNftAction::RoyaltyInfo {
+            token_id,
+            sale_price,
+        } => nft_contract.royalty_info(token_id, sale_price),
+        ComposableNftAction::SetTokenRoyalty { token_id, royalty } => {
+            nft_contract.set_token_royalty(token_id, royalty)
+        }
     };
 
     msg::reply(result, 0).expect("Failed to encode or reply with `StudentNftEvent`.");
@@ -484,12 +658,14 @@ impl From<ComposableNftContract> for ComposableNftState {
             tokens,
             owners,
             token_approvals,
+            operator_approvals,
             config,
             nonce,
             collection_owner,
             img_links,
             restriction_mint,
             number_combination,
+            token_royalties,
             ..
             // combinations,
             // number_combination
@@ -507,21 +683,28 @@ impl From<ComposableNftContract> for ComposableNftState {
             .iter()
             .map(|(nft_id, actor_id)| (*nft_id, *actor_id))
             .collect();
+        let operator_approvals = operator_approvals
+            .into_iter()
+            .map(|(owner, operators)| (owner, operators.into_iter().collect()))
+            .collect();
         let restriction_mint = restriction_mint
             .iter()
             .map(|(id, number)| (*id, *number))
             .collect();
+        let token_royalties = token_royalties.into_iter().collect();
 
         Self {
             tokens,
             owners,
             token_approvals,
+            operator_approvals,
             config,
             nonce,
             img_links,
             collection_owner,
             restriction_mint,
             number_combination,
+            token_royalties,
         }
     }
 }
@@ -2,6 +2,8 @@
 use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId, CodeId};
 
+pub use common_io::{Capabilities, RoyaltyInfo, PROTOCOL_VERSION};
+
 pub struct NftMarketplaceMetadata;
 impl Metadata for NftMarketplaceMetadata {
     type Init = In<NftMarketplaceInit>;
@@ -20,6 +22,7 @@ impl Metadata for NftMarketplaceMetadata {
 /// (a message is sent to the collection contract to see if the collection can be deleted)
 /// * gas_for_get_token_info - gas which is needed to get information from the collection about the token
 /// (used for sale, auction and offers)
+/// * gas_for_cleanup_offers - gas which is needed to send a delayed message to return the value of an expired offer
 /// * time_between_create_collections - time between collection creation 
 /// (to avoid regular users from creating collections too often)
 /// * minimum_transfer_value - minimum allowable transfer value
@@ -32,6 +35,7 @@ pub struct NftMarketplaceInit {
     pub gas_for_close_auction: u64,
     pub gas_for_delete_collection: u64,
     pub gas_for_get_token_info: u64, 
+    pub gas_for_cleanup_offers: u64,
     pub time_between_create_collections: u64,
     pub minimum_transfer_value: u128,
     pub ms_in_block: u32,
@@ -45,10 +49,28 @@ pub enum NftMarketplaceAction {
         type_name: String,
         type_description: String,
     },
+    UpdateCollectionType {
+        type_name: String,
+        code_id: CodeId,
+        meta_link: String,
+    },
+    DeprecateCollectionType {
+        type_name: String,
+    },
+    RemoveCollectionType {
+        type_name: String,
+    },
     CreateCollection {
         type_name: String,
         payload: Vec<u8>,
     },
+    /// `token_id` - any existing token of the collection,
+    /// the collection owner is taken from the information about this token
+    RegisterExternalCollection {
+        address: ActorId,
+        type_name: String,
+        token_id: u64,
+    },
     SaleNft {
         collection_address: ActorId,
         token_id: u64,
@@ -61,6 +83,8 @@ pub enum NftMarketplaceAction {
     BuyNft {
         collection_address: ActorId,
         token_id: u64,
+        recipient: Option<ActorId>,
+        referrer: Option<ActorId>,
     },
     CreateAuction {
         collection_address: ActorId,
@@ -71,6 +95,8 @@ pub enum NftMarketplaceAction {
     AddBid {
         collection_address: ActorId,
         token_id: u64,
+        recipient: Option<ActorId>,
+        referrer: Option<ActorId>,
     },
     CloseAuction {
         collection_address: ActorId,
@@ -83,6 +109,9 @@ pub enum NftMarketplaceAction {
     CreateOffer {
         collection_address: ActorId,
         token_id: u64,
+        expires_at: Option<u64>,
+        recipient: Option<ActorId>,
+        referrer: Option<ActorId>,
     },
     CancelOffer {
         collection_address: ActorId,
@@ -91,6 +120,152 @@ pub enum NftMarketplaceAction {
     AcceptOffer {
         offer: Offer,
     },
+    CleanupExpiredOffers {
+        offers: Vec<Offer>,
+    },
+    CounterOffer {
+        offer: Offer,
+        price: u128,
+    },
+    AcceptCounterOffer {
+        offer: Offer,
+    },
+    CreateCollectionOffer {
+        collection_address: ActorId,
+        quantity: u64,
+    },
+    CancelCollectionOffer {
+        collection_address: ActorId,
+    },
+    AcceptCollectionOffer {
+        offer: CollectionOffer,
+        token_id: u64,
+    },
+    CreateTraitOffer {
+        collection_address: ActorId,
+        layer: u32,
+        option: u8,
+    },
+    CancelTraitOffer {
+        collection_address: ActorId,
+        layer: u32,
+        option: u8,
+    },
+    AcceptTraitOffer {
+        offer: TraitOffer,
+        token_id: u64,
+    },
+    ProposeSwap {
+        counterparty: ActorId,
+        proposer_items: Vec<(ActorId, u64)>,
+        counterparty_items: Vec<(ActorId, u64)>,
+        expires_at: Option<u64>,
+    },
+    AcceptSwap {
+        swap_id: u64,
+    },
+    CancelSwap {
+        swap_id: u64,
+    },
+    ExpireSwap {
+        swap_id: u64,
+    },
+    ReleaseToken {
+        collection_address: ActorId,
+        token_id: u64,
+    },
+    ListForRent {
+        collection_address: ActorId,
+        token_id: u64,
+        price_per_day: u128,
+        max_days: u32,
+    },
+    Rent {
+        collection_address: ActorId,
+        token_id: u64,
+        days: u32,
+    },
+    CancelRentListing {
+        collection_address: ActorId,
+        token_id: u64,
+    },
+    RequestLoan {
+        collection_address: ActorId,
+        token_id: u64,
+        amount: u128,
+        interest: u128,
+        duration_ms: u64,
+    },
+    CancelLoanRequest {
+        loan_id: u64,
+    },
+    FundLoan {
+        loan_id: u64,
+    },
+    RepayLoan {
+        loan_id: u64,
+    },
+    ClaimCollateral {
+        loan_id: u64,
+    },
+    SetVaultCode {
+        code_id: CodeId,
+    },
+    CreateVault {
+        collection_address: ActorId,
+        token_id: u64,
+        total_shares: u128,
+        reserve_price: u128,
+        buyout_duration_ms: u64,
+    },
+    CloseVault,
+    CreateRaffle {
+        collection_address: ActorId,
+        token_id: u64,
+        ticket_price: u128,
+        max_tickets: u32,
+        min_tickets: u32,
+        ends_at: u64,
+    },
+    BuyTickets {
+        collection_address: ActorId,
+        token_id: u64,
+        count: u32,
+    },
+    DrawRaffle {
+        collection_address: ActorId,
+        token_id: u64,
+    },
+    BuyMany {
+        items: Vec<(ActorId, u64)>,
+        max_total: u128,
+    },
+    BuyCheapest {
+        collection_address: ActorId,
+        count: u32,
+        max_total: u128,
+    },
+    SetReferralRate {
+        collection_address: ActorId,
+        token_id: u64,
+        referral_rate: Option<u16>,
+    },
+    BuySignedOrder {
+        order: SignedOrder,
+        signature: [u8; 64],
+    },
+    CancelOrderNonce {
+        nonce: u64,
+    },
+    SaleNftNonCustodial {
+        collection_address: ActorId,
+        token_id: u64,
+        price: u128,
+    },
+    InvalidateListing {
+        collection_address: ActorId,
+        token_id: u64,
+    },
     DeleteCollection {
         collection_address: ActorId,
     },
@@ -106,6 +281,7 @@ pub enum NftMarketplaceAction {
         gas_for_close_auction: Option<u64>,
         gas_for_delete_collection: Option<u64>,
         gas_for_get_token_info: Option<u64>,
+        gas_for_cleanup_offers: Option<u64>,
         time_between_create_collections: Option<u64>,
         minimum_transfer_value: Option<u128>,
         ms_in_block: Option<u32>,
@@ -120,10 +296,31 @@ pub enum NftMarketplaceEvent {
         type_name: String,
         type_description: String,
     },
+    CollectionTypeUpdated {
+        type_name: String,
+        code_id: CodeId,
+        meta_link: String,
+        version: u32,
+    },
+    CollectionTypeDeprecated {
+        type_name: String,
+    },
+    CollectionTypeRemoved {
+        type_name: String,
+    },
     CollectionCreated {
         type_name: String,
         collection_address: ActorId,
     },
+    UnsupportedCollectionCreated {
+        type_name: String,
+        collection_address: ActorId,
+    },
+    ExternalCollectionRegistered {
+        type_name: String,
+        collection_address: ActorId,
+        owner: ActorId,
+    },
     SaleNft {
         collection_address: ActorId,
         token_id: u64,
@@ -139,6 +336,7 @@ pub enum NftMarketplaceEvent {
         token_id: u64,
         price: u128,
         current_owner: ActorId,
+        referral: Option<(ActorId, u128)>,
     },
     AuctionCreated {
         collection_address: ActorId,
@@ -151,6 +349,7 @@ pub enum NftMarketplaceEvent {
         token_id: u64,
         price: u128,
         current_owner: ActorId,
+        referral: Option<(ActorId, u128)>,
     },
     BidAdded {
         collection_address: ActorId,
@@ -165,6 +364,7 @@ pub enum NftMarketplaceEvent {
         collection_address: ActorId,
         token_id: u64,
         price: u128,
+        expires_at: Option<u64>,
     },
     OfferCanceled {
         collection_address: ActorId,
@@ -172,6 +372,155 @@ pub enum NftMarketplaceEvent {
     },
     OfferAccepted {
         offer: Offer,
+        referral: Option<(ActorId, u128)>,
+    },
+    ExpiredOffersCleanedUp {
+        offers: Vec<Offer>,
+    },
+    CounterOfferCreated {
+        offer: Offer,
+        price: u128,
+    },
+    CounterOfferAccepted {
+        offer: Offer,
+        price: u128,
+        referral: Option<(ActorId, u128)>,
+    },
+    CollectionOfferCreated {
+        collection_address: ActorId,
+        price: u128,
+        quantity: u64,
+    },
+    CollectionOfferCanceled {
+        collection_address: ActorId,
+    },
+    CollectionOfferAccepted {
+        offer: CollectionOffer,
+        token_id: u64,
+        price: u128,
+    },
+    TraitOfferCreated {
+        collection_address: ActorId,
+        layer: u32,
+        option: u8,
+        price: u128,
+    },
+    TraitOfferCanceled {
+        collection_address: ActorId,
+        layer: u32,
+        option: u8,
+    },
+    TraitOfferAccepted {
+        offer: TraitOffer,
+        token_id: u64,
+        price: u128,
+    },
+    SwapProposed {
+        swap_id: u64,
+        swap: Swap,
+    },
+    SwapAccepted {
+        swap_id: u64,
+    },
+    SwapCanceled {
+        swap_id: u64,
+    },
+    TokenReleased {
+        collection_address: ActorId,
+        token_id: u64,
+        to: ActorId,
+    },
+    ListedForRent {
+        collection_address: ActorId,
+        token_id: u64,
+        price_per_day: u128,
+        max_days: u32,
+    },
+    Rented {
+        collection_address: ActorId,
+        token_id: u64,
+        user: ActorId,
+        expires: u64,
+    },
+    RentListingCanceled {
+        collection_address: ActorId,
+        token_id: u64,
+    },
+    LoanRequested {
+        loan_id: u64,
+        loan: Loan,
+    },
+    LoanRequestCanceled {
+        loan_id: u64,
+    },
+    LoanFunded {
+        loan_id: u64,
+        lender: ActorId,
+        repay_until: u64,
+    },
+    LoanRepaid {
+        loan_id: u64,
+    },
+    CollateralClaimed {
+        loan_id: u64,
+    },
+    VaultCodeSet {
+        code_id: CodeId,
+    },
+    VaultCreated {
+        vault_address: ActorId,
+        collection_address: ActorId,
+        token_id: u64,
+    },
+    VaultClosed {
+        vault_address: ActorId,
+        collection_address: ActorId,
+        token_id: u64,
+    },
+    RaffleCreated {
+        collection_address: ActorId,
+        token_id: u64,
+        ticket_price: u128,
+        max_tickets: u32,
+        min_tickets: u32,
+        ends_at: u64,
+    },
+    TicketsBought {
+        collection_address: ActorId,
+        token_id: u64,
+        buyer: ActorId,
+        count: u32,
+    },
+    RaffleDrawn {
+        collection_address: ActorId,
+        token_id: u64,
+        winner: Option<ActorId>,
+    },
+    NftsBought {
+        results: Vec<((ActorId, u64), Result<u128, NftMarketplaceError>)>,
+        total: u128,
+    },
+    ReferralRateSet {
+        collection_address: ActorId,
+        token_id: u64,
+        referral_rate: Option<u16>,
+    },
+    SignedOrderFilled {
+        order: SignedOrder,
+        buyer: ActorId,
+    },
+    OrderNonceCanceled {
+        seller: ActorId,
+        nonce: u64,
+    },
+    OrdersMatched {
+        collection_address: ActorId,
+        trades: Vec<Trade>,
+        remaining_offer: Option<CollectionOfferInfo>,
+    },
+    ListingInvalidated {
+        collection_address: ActorId,
+        token_id: u64,
     },
     CollectionDeleted {
         collection_address: ActorId,
@@ -188,6 +537,7 @@ pub enum NftMarketplaceEvent {
         gas_for_close_auction: Option<u64>,
         gas_for_delete_collection: Option<u64>,
         gas_for_get_token_info: Option<u64>,
+        gas_for_cleanup_offers: Option<u64>,
         time_between_create_collections: Option<u64>,
         minimum_transfer_value: Option<u128>,
         ms_in_block: Option<u32>,
@@ -205,6 +555,13 @@ pub enum StateQuery {
     Config,
     AllCollections,
     GetCollectionInfo(ActorId),
+    OpenLoans,
+    ActiveLoans,
+    BestBidAsk(ActorId),
+    OrderBookDepth {
+        collection_address: ActorId,
+        depth: u32,
+    },
 }
 
 #[derive(Encode, Decode, TypeInfo)]
@@ -215,6 +572,16 @@ pub enum StateReply {
     Config(Config),
     AllCollections(Vec<(ActorId, (String, ActorId))>),
     CollectionInfo(Option<CollectionInfo>),
+    OpenLoans(Vec<(u64, Loan)>),
+    ActiveLoans(Vec<(u64, Loan)>),
+    BestBidAsk {
+        best_bid: Option<u128>,
+        best_ask: Option<u128>,
+    },
+    OrderBookDepth {
+        asks: Vec<(u128, u64)>,
+        bids: Vec<(u128, u64)>,
+    },
 }
 
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
@@ -225,7 +592,22 @@ pub struct State {
     pub type_collections: Vec<(String, TypeCollectionInfo)>,
     pub sales: Vec<((ActorId, u64), NftInfoForSale)>,
     pub auctions: Vec<((ActorId, u64), Auction)>,
-    pub offers: Vec<(Offer, u128)>,
+    pub offers: Vec<(Offer, OfferInfo)>,
+    pub counter_offers: Vec<(Offer, (ActorId, u128))>,
+    pub collection_offers: Vec<(CollectionOffer, CollectionOfferInfo)>,
+    pub trait_offers: Vec<(TraitOffer, u128)>,
+    pub swaps: Vec<(u64, Swap)>,
+    pub unreleased_tokens: Vec<((ActorId, u64), ActorId)>,
+    pub rentals: Vec<((ActorId, u64), Rental)>,
+    pub loans: Vec<(u64, Loan)>,
+    pub vault_code_id: Option<CodeId>,
+    pub vaults: Vec<(ActorId, (ActorId, u64))>,
+    pub raffles: Vec<((ActorId, u64), Raffle)>,
+    pub used_order_nonces: Vec<(ActorId, u64)>,
+    pub collection_capabilities: Vec<(ActorId, Capabilities)>,
+    pub imported_collections: Vec<ActorId>,
+    pub collection_type_versions: Vec<(ActorId, u32)>,
+    pub unsupported_collections: Vec<(ActorId, (String, ActorId))>,
     pub config: Config,
 }
 
@@ -233,18 +615,36 @@ pub struct State {
 /// the admin should preload the NFT contract and specify in the marketplace so that regular users can use it
 /// * meta_link -  it is necessary to set a reference where the meta of this collection type will be stored for further interaction with the contract
 /// * type_description - description of this type of collection
+/// * version - version of the current code of this type, starting from 1
+/// * previous_versions - code_id and meta_link of the earlier versions, starting from the first one
+/// * deprecated - new collections of a deprecated type can't be created, but the existing ones are still traded
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 pub struct TypeCollectionInfo {
     pub code_id: CodeId,
     pub meta_link: String,
     pub type_description: String,
+    pub version: u32,
+    pub previous_versions: Vec<(CodeId, String)>,
+    pub deprecated: bool,
 }
 
+/// * type_version - version of the collection type that the collection was created with,
+/// meta_link belongs to this version
+/// * origin - whether the collection was deployed by the marketplace or deployed elsewhere and imported
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 pub struct CollectionInfo {
     pub owner: ActorId,
     pub type_name: String,
+    pub type_version: u32,
     pub meta_link: String,
+    pub capabilities: Capabilities,
+    pub origin: CollectionOrigin,
+}
+
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionOrigin {
+    DeployedByMarketplace,
+    Imported,
 }
 
 
@@ -255,19 +655,32 @@ pub struct Config {
     pub gas_for_close_auction: u64,
     pub gas_for_delete_collection: u64,
     pub gas_for_get_token_info: u64,
+    pub gas_for_cleanup_offers: u64,
     pub time_between_create_collections: u64,
     pub minimum_transfer_value: u128,
     pub ms_in_block: u32,
 }
 
+/// * royalty_recipients - accounts that receive the royalty and their shares of the price (in basis points)
+/// * referral_rate - share of the price (in basis points) that the seller has agreed to pay
+/// to the referrer of the buyer, None if no referral fee is paid
+/// * custodial - whether the token is kept by the marketplace,
+/// otherwise it stays with the owner who has approved it to the marketplace
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 pub struct NftInfoForSale {
     pub price: u128,
     pub token_owner: ActorId,
-    pub collection_owner: ActorId,
-    pub royalty: u16,
+    pub royalty_recipients: Vec<(ActorId, u16)>,
+    pub referral_rate: Option<u16>,
+    pub custodial: bool,
 }
 
+/// * current_winner - the highest bidder, who gets the value back if the bid is outbid
+/// * recipient - account that receives the token if the current bid wins,
+/// None if it is the current winner
+/// * referrer - referrer of the current winner
+/// * referral_rate - share of the price (in basis points) that the seller has agreed to pay
+/// to the referrer of the buyer, None if no referral fee is paid
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 pub struct Auction {
     pub owner: ActorId,
@@ -275,8 +688,10 @@ pub struct Auction {
     pub ended_at: u64,
     pub current_price: u128,
     pub current_winner: ActorId,
-    pub collection_owner: ActorId,
-    pub royalty: u16,
+    pub recipient: Option<ActorId>,
+    pub referrer: Option<ActorId>,
+    pub royalty_recipients: Vec<(ActorId, u16)>,
+    pub referral_rate: Option<u16>,
 }
 #[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq, Hash)]
 pub struct Offer {
@@ -284,6 +699,136 @@ pub struct Offer {
     pub token_id: u64,
     pub creator: ActorId,
 }
+
+/// * price - value that the creator has attached to the offer
+/// * expires_at - time (in ms) after which the offer can no longer be accepted
+/// and the value is returned to the creator
+/// * recipient - account that receives the token when the offer is accepted,
+/// None if it is the creator
+/// * referrer - account that receives the referral fee when the offer is accepted,
+/// if the seller has set the referral rate of the listing
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct OfferInfo {
+    pub price: u128,
+    pub expires_at: Option<u64>,
+    pub recipient: Option<ActorId>,
+    pub referrer: Option<ActorId>,
+}
+
+/// An offer for any token of the collection
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq, Hash)]
+pub struct CollectionOffer {
+    pub collection_address: ActorId,
+    pub creator: ActorId,
+}
+
+/// * price - price offered for one token
+/// * quantity - number of tokens the creator is still willing to buy
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct CollectionOfferInfo {
+    pub price: u128,
+    pub quantity: u64,
+}
+
+/// A sale of a listed token to a collection offer made by the order book
+/// * price - price of the order that was in the book first
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct Trade {
+    pub token_id: u64,
+    pub seller: ActorId,
+    pub buyer: ActorId,
+    pub price: u128,
+}
+
+/// An offer for any token of a composable collection
+/// whose combination has the given option in the given layer
+/// * layer - index of the layer in the token combination
+/// * option - index of the option chosen in this layer
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq, Hash)]
+pub struct TraitOffer {
+    pub collection_address: ActorId,
+    pub layer: u32,
+    pub option: u8,
+    pub creator: ActorId,
+}
+
+/// * price_per_day - rental price for one day
+/// * max_days - maximum number of days for which the token can be rented at a time
+/// * user - current renter and time (in ms) until which the token is rented
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct Rental {
+    pub owner: ActorId,
+    pub price_per_day: u128,
+    pub max_days: u32,
+    pub royalty_recipients: Vec<(ActorId, u16)>,
+    pub user: Option<(ActorId, u64)>,
+}
+
+/// * collection_address, token_id - the token that is kept by the marketplace as collateral
+/// * amount - value that the borrower receives from the lender
+/// * interest - value that the borrower pays to the lender in addition to the amount
+/// * duration_ms - loan term, counted from the moment the loan is funded
+/// * lender - lender and time (in ms) until which the loan must be repaid,
+/// None if the loan has not been funded yet
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct Loan {
+    pub borrower: ActorId,
+    pub collection_address: ActorId,
+    pub token_id: u64,
+    pub amount: u128,
+    pub interest: u128,
+    pub duration_ms: u64,
+    pub lender: Option<(ActorId, u64)>,
+}
+
+/// * max_tickets - maximum number of tickets, at most 10 000
+/// * min_tickets - minimum number of tickets that must be sold, otherwise the buyers are refunded
+/// * ends_at - time (in ms) after which the tickets can no longer be bought and the raffle is drawn
+/// * tickets - buyers of the tickets, one entry per ticket
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+pub struct Raffle {
+    pub owner: ActorId,
+    pub ticket_price: u128,
+    pub max_tickets: u32,
+    pub min_tickets: u32,
+    pub ends_at: u64,
+    pub royalty_recipients: Vec<(ActorId, u16)>,
+    pub tickets: Vec<ActorId>,
+}
+
+/// A listing signed off-chain by the seller with the sr25519 key of the account.
+/// The signature covers `(marketplace_address, order).encode()` with the "substrate" signing context.
+/// * expires_at - time (in ms) after which the order can no longer be filled
+/// * nonce - number that is used once, the seller can cancel the order by canceling the nonce
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct SignedOrder {
+    pub seller: ActorId,
+    pub collection_address: ActorId,
+    pub token_id: u64,
+    pub price: u128,
+    pub expires_at: u64,
+    pub nonce: u64,
+}
+
+/// * proposer_items - tokens (collection address, token id) that the proposer gives,
+/// they are kept by the marketplace until the swap is accepted, canceled or expired
+/// * counterparty_items - tokens that the proposer wants to receive from the counterparty
+/// * value - value that the proposer gives in addition to the tokens
+/// * expires_at - time (in ms) after which the swap can no longer be accepted
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+pub struct Swap {
+    pub proposer: ActorId,
+    pub counterparty: ActorId,
+    pub proposer_items: Vec<(ActorId, u64)>,
+    pub counterparty_items: Vec<(ActorId, u64)>,
+    pub value: u128,
+    pub expires_at: Option<u64>,
+}
+
+/// Actions that the marketplace sends to the collection contracts.
+/// Every collection contract must start its action and event enums with these variants
+/// in the same order, new ones are added to the end of this list.
+/// A contract that does not support some of them replies with an error.
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
 pub enum NftAction {
     Transfer {
@@ -299,6 +844,23 @@ pub enum NftAction {
         token_id: u64,
     },
     CanDelete,
+    GetTokenCombination {
+        token_id: u64,
+    },
+    SetUser {
+        token_id: u64,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfo {
+        token_id: u64,
+        sale_price: u128,
+    },
+    IsApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+    },
+    GetCapabilities,
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
@@ -314,8 +876,24 @@ pub enum NftEvent {
         sellable: bool,
         collection_owner: ActorId,
         royalty: u16,
+        user: Option<(ActorId, u64)>,
+        royalty_recipients: Vec<(ActorId, u16)>,
     },
     CanDelete(bool),
+    TokenCombinationReceived {
+        combination: Vec<u8>,
+    },
+    UserSet {
+        token_id: u64,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfoReceived(RoyaltyInfo),
+    IsApprovedForAll(bool),
+    CapabilitiesReceived {
+        capabilities: Capabilities,
+        collection_owner: ActorId,
+    },
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
@@ -2,6 +2,12 @@
 
 use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId};
+
+pub use common_io::{
+    royalty_recipients_are_valid, scale_royalty_recipients, Capabilities, RoyaltyInfo,
+    PROTOCOL_VERSION,
+};
+
 pub type NftId = u64;
 pub type TimeSec = u32;
 
@@ -51,6 +57,7 @@ pub struct Config {
     pub listening_capabilities: ListenCapability,
     pub additional_links: Option<AdditionalLinks>,
     pub royalty: u16,
+    pub royalty_recipients: Vec<(ActorId, u16)>,
     pub payment_for_mint: u128,
     pub transferable: Option<u64>,
     pub sellable: Option<u64>,
@@ -87,6 +94,23 @@ pub enum MusicNftAction {
         token_id: NftId,
     },
     CanDelete,
+    GetTokenCombination {
+        token_id: NftId,
+    },
+    SetUser {
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfo {
+        token_id: NftId,
+        sale_price: u128,
+    },
+    IsApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+    },
+    GetCapabilities,
     Mint,
     Approve {
         to: ActorId,
@@ -95,6 +119,10 @@ pub enum MusicNftAction {
     RevokeApproval {
         token_id: NftId,
     },
+    ApproveForAll {
+        operator: ActorId,
+        approved: bool,
+    },
     Expand {
         additional_links: Vec<(Links, ImageData)>,
     },
@@ -109,6 +137,10 @@ pub enum MusicNftAction {
         token_id: NftId,
         metadata: String,
     },
+    SetTokenRoyalty {
+        token_id: NftId,
+        royalty: Option<u16>,
+    },
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
@@ -124,8 +156,24 @@ pub enum MusicNftEvent {
         sellable: bool,
         collection_owner: ActorId,
         royalty: u16,
+        user: Option<(ActorId, u64)>,
+        royalty_recipients: Vec<(ActorId, u16)>,
     },
     CanDelete(bool),
+    TokenCombinationReceived {
+        combination: Vec<u8>,
+    },
+    UserSet {
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfoReceived(RoyaltyInfo),
+    IsApprovedForAll(bool),
+    CapabilitiesReceived {
+        capabilities: Capabilities,
+        collection_owner: ActorId,
+    },
     Initialized {
         config: Config,
     },
@@ -140,6 +188,11 @@ pub enum MusicNftEvent {
     ApprovalRevoked {
         token_id: NftId,
     },
+    ApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+        approved: bool,
+    },
     Expanded {
         additional_links: Vec<(Links, ImageData)>,
     },
@@ -154,6 +207,10 @@ pub enum MusicNftEvent {
         token_id: NftId,
         metadata: String,
     },
+    TokenRoyaltySet {
+        token_id: NftId,
+        royalty: Option<u16>,
+    },
 }
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
 pub struct MusicNftError(pub String);
@@ -163,6 +220,9 @@ pub struct NftState {
     pub tokens: Vec<(NftId, Nft)>,
     pub owners: Vec<(ActorId, Vec<NftId>)>,
     pub token_approvals: Vec<(NftId, ActorId)>,
+    pub operator_approvals: Vec<(ActorId, Vec<ActorId>)>,
+    pub users: Vec<(NftId, (ActorId, u64))>,
+    pub token_royalties: Vec<(NftId, u16)>,
     pub config: Config,
     pub nonce: NftId,
     pub links_and_data: Vec<(Links, ImageData)>,
@@ -186,6 +246,7 @@ pub enum StateQuery {
     Name,
     Description,
     Config,
+    ListenCapability { account: ActorId, token_id: NftId },
     All,
 }
 
@@ -194,6 +255,7 @@ pub enum StateReply {
     Name(String),
     Description(String),
     Config(Config),
+    ListenCapability(ListenCapability),
     All(NftState),
 }
 
@@ -13,6 +13,12 @@ struct NftContract {
     pub owners: HashMap<ActorId, HashSet<NftId>>,
     pub restriction_mint: HashMap<ActorId, u32>, // made in order to track the number of mint nft
     pub token_approvals: HashMap<NftId, ActorId>,
+    // operators approved by the owner to transfer all of the owner's tokens
+    pub operator_approvals: HashMap<ActorId, HashSet<ActorId>>,
+    // user of the token and the time (in ms) until which the user's rights are valid
+    pub users: HashMap<NftId, (ActorId, u64)>,
+    // royalty of the tokens that differs from the royalty of the collection
+    pub token_royalties: HashMap<NftId, u16>,
     pub config: Config,
     pub nonce: NftId,
     pub links_and_data: Vec<(Links, ImageData)>,
@@ -148,6 +154,10 @@ impl NftContract {
             .or_insert_with(|| HashSet::from([token_id]));
 
         self.token_approvals.remove(&token_id);
+        // the rights of an active user survive the transfer until they expire
+        if self.active_user(token_id).is_none() {
+            self.users.remove(&token_id);
+        }
 
         Ok(MusicNftEvent::Transferred {
             owner: *from,
@@ -184,6 +194,40 @@ impl NftContract {
         Ok(MusicNftEvent::ApprovalRevoked { token_id })
     }
 
+    fn approve_for_all(
+        &mut self,
+        operator: &ActorId,
+        approved: bool,
+    ) -> Result<MusicNftEvent, MusicNftError> {
+        let owner = msg::source();
+        if owner == *operator {
+            return Err(MusicNftError("Self approval is not allowed".to_owned()));
+        }
+        if approved {
+            self.operator_approvals
+                .entry(owner)
+                .or_default()
+                .insert(*operator);
+        } else if let Some(operators) = self.operator_approvals.get_mut(&owner) {
+            operators.remove(operator);
+            if operators.is_empty() {
+                self.operator_approvals.remove(&owner);
+            }
+        }
+
+        Ok(MusicNftEvent::ApprovedForAll {
+            owner,
+            operator: *operator,
+            approved,
+        })
+    }
+
+    fn is_approved_for_all(&self, owner: &ActorId, operator: &ActorId) -> bool {
+        self.operator_approvals
+            .get(owner)
+            .map_or(false, |operators| operators.contains(operator))
+    }
+
     fn get_token_info(&self, token_id: NftId) -> Result<MusicNftEvent, MusicNftError> {
         let nft = self.tokens.get(&token_id);
         let (token_owner, can_sell) = if let Some(nft) = nft {
@@ -199,15 +243,139 @@ impl NftContract {
             ));
         };
         let approval = self.token_approvals.get(&token_id).copied();
+        let (royalty, royalty_recipients) = self.token_royalty(token_id);
 
         Ok(MusicNftEvent::TokenInfoReceived {
             token_owner,
             approval,
             sellable: can_sell,
             collection_owner: self.collection_owner,
-            royalty: self.config.royalty,
+            royalty,
+            user: self.active_user(token_id),
+            royalty_recipients,
         })
     }
+    fn set_user(
+        &mut self,
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    ) -> Result<MusicNftEvent, MusicNftError> {
+        if let Some(nft) = self.tokens.get(&token_id) {
+            let msg_src = msg::source();
+            if nft.owner != msg_src && !self.is_approved_for_all(&nft.owner, &msg_src) {
+                self.check_approve(&msg_src, &token_id)?;
+            }
+        } else {
+            return Err(MusicNftError(
+                "NonFungibleToken: token does not exist".to_owned(),
+            ));
+        }
+        if expires <= exec::block_timestamp() {
+            return Err(MusicNftError(
+                "The expiration time must be in the future".to_owned(),
+            ));
+        }
+        if matches!(self.active_user(token_id), Some((current, _)) if current != user) {
+            return Err(MusicNftError(
+                "The token is already used by another user".to_owned(),
+            ));
+        }
+        self.users.insert(token_id, (user, expires));
+
+        Ok(MusicNftEvent::UserSet {
+            token_id,
+            user,
+            expires,
+        })
+    }
+    fn active_user(&self, token_id: NftId) -> Option<(ActorId, u64)> {
+        self.users
+            .get(&token_id)
+            .filter(|(_, expires)| *expires > exec::block_timestamp())
+            .copied()
+    }
+    /// The active user of the token, or its owner if there is none, can listen to the full track
+    fn listen_capability(&self, account: &ActorId, token_id: NftId) -> ListenCapability {
+        let holder = match self.active_user(token_id) {
+            Some((user, _)) => Some(user),
+            None => self.tokens.get(&token_id).map(|nft| nft.owner),
+        };
+        if holder == Some(*account) {
+            ListenCapability::AvailableFull
+        } else {
+            self.config.listening_capabilities.clone()
+        }
+    }
+    fn set_token_royalty(
+        &mut self,
+        token_id: NftId,
+        royalty: Option<u16>,
+    ) -> Result<MusicNftEvent, MusicNftError> {
+        self.check_collection_owner()?;
+        let token_owner = if let Some(nft) = self.tokens.get(&token_id) {
+            nft.owner
+        } else {
+            return Err(MusicNftError(
+                "NonFungibleToken: token does not exist".to_owned(),
+            ));
+        };
+        // made 10_000 so you can enter hundredths of a percent.
+        if royalty.map_or(false, |royalty| royalty > 10_000) {
+            return Err(MusicNftError(
+                "Royalty percent must be less than 100%".to_owned(),
+            ));
+        }
+        // the holder of the token has agreed to the current royalty,
+        // so it can't be raised on the token that the collection owner has given away
+        let new_royalty = royalty.unwrap_or(self.config.royalty);
+        if token_owner != self.collection_owner && new_royalty > self.token_royalty(token_id).0 {
+            return Err(MusicNftError(
+                "The royalty of the token held by another account can only be lowered".to_owned(),
+            ));
+        }
+        if let Some(royalty) = royalty {
+            self.token_royalties.insert(token_id, royalty);
+        } else {
+            self.token_royalties.remove(&token_id);
+        }
+
+        Ok(MusicNftEvent::TokenRoyaltySet { token_id, royalty })
+    }
+    /// The royalty of the token and the shares of the royalty recipients,
+    /// the shares are scaled to the royalty of the token if it is overridden
+    fn token_royalty(&self, token_id: NftId) -> (u16, Vec<(ActorId, u16)>) {
+        match self.token_royalties.get(&token_id) {
+            Some(&royalty) => (
+                royalty,
+                scale_royalty_recipients(
+                    self.config.royalty,
+                    &self.config.royalty_recipients,
+                    royalty,
+                ),
+            ),
+            None => (self.config.royalty, self.config.royalty_recipients.clone()),
+        }
+    }
+    fn royalty_info(
+        &self,
+        token_id: NftId,
+        sale_price: u128,
+    ) -> Result<MusicNftEvent, MusicNftError> {
+        if !self.tokens.contains_key(&token_id) {
+            return Err(MusicNftError(
+                "NonFungibleToken: token does not exist".to_owned(),
+            ));
+        }
+        let (royalty, royalty_recipients) = self.token_royalty(token_id);
+
+        Ok(MusicNftEvent::RoyaltyInfoReceived(RoyaltyInfo::new(
+            self.collection_owner,
+            royalty,
+            &royalty_recipients,
+            sale_price,
+        )))
+    }
     fn expand(
         &mut self,
         additional_links: Vec<(Links, ImageData)>,
@@ -249,6 +417,11 @@ impl NftContract {
                 "Royalty percent must be less than 100%".to_owned(),
             ));
         }
+        if !royalty_recipients_are_valid(config.royalty, &config.royalty_recipients) {
+            return Err(MusicNftError(
+                "The shares of the royalty recipients must add up to the royalty".to_owned(),
+            ));
+        }
 
         if config.transferable.is_none() && config.sellable.is_some() {
             return Err(MusicNftError("Tokens must be transferable".to_owned()));
@@ -321,6 +494,17 @@ impl NftContract {
         Ok(MusicNftEvent::MetadataAdded { token_id, metadata })
     }
 
+    fn get_capabilities(&self) -> Result<MusicNftEvent, MusicNftError> {
+        Ok(MusicNftEvent::CapabilitiesReceived {
+            capabilities: Capabilities {
+                version: PROTOCOL_VERSION,
+                operator_approvals: true,
+                rentals: true,
+                token_combinations: false,
+            },
+            collection_owner: self.collection_owner,
+        })
+    }
     fn can_delete(&self) -> Result<MusicNftEvent, MusicNftError> {
         Ok(MusicNftEvent::CanDelete(self.tokens.is_empty()))
     }
@@ -403,7 +587,7 @@ impl NftContract {
                 return Err(MusicNftError("NonFungibleToken: access denied".to_owned()));
             }
             let msg_src = msg::source();
-            if owner != msg_src {
+            if owner != msg_src && !self.is_approved_for_all(&owner, &msg_src) {
                 self.check_approve(&msg_src, token_id)?;
             }
             if let Some(time) = self.config.transferable {
@@ -461,6 +645,10 @@ extern "C" fn init() {
     if config.royalty > 10_000 {
         panic!("Royalty percent must be less than 100%");
     }
+    assert!(
+        royalty_recipients_are_valid(config.royalty, &config.royalty_recipients),
+        "The shares of the royalty recipients must add up to the royalty"
+    );
     assert!(
         !links_and_data.is_empty(),
         "There must be at least one link to create a collection"
@@ -481,6 +669,9 @@ extern "C" fn init() {
             tokens: HashMap::new(),
             owners: HashMap::new(),
             token_approvals: HashMap::new(),
+            operator_approvals: HashMap::new(),
+            users: HashMap::new(),
+            token_royalties: HashMap::new(),
             restriction_mint: HashMap::new(),
             config: config.clone(),
             nonce: 0,
@@ -527,6 +718,12 @@ extern "C" fn handle() {
         }
         MusicNftAction::Approve { to, token_id } => nft_contract.approve(&to, token_id),
         MusicNftAction::RevokeApproval { token_id } => nft_contract.revoke_approve(token_id),
+        MusicNftAction::ApproveForAll { operator, approved } => {
+            nft_contract.approve_for_all(&operator, approved)
+        }
+        MusicNftAction::IsApprovedForAll { owner, operator } => Ok(
+            MusicNftEvent::IsApprovedForAll(nft_contract.is_approved_for_all(&owner, &operator)),
+        ),
         MusicNftAction::Expand { additional_links } => nft_contract.expand(additional_links),
         MusicNftAction::ChangeConfig { config } => nft_contract.change_config(config),
         MusicNftAction::ChangeImg { token_id, img_link } => {
@@ -537,6 +734,22 @@ extern "C" fn handle() {
         }
         MusicNftAction::GetTokenInfo { token_id } => nft_contract.get_token_info(token_id),
         MusicNftAction::CanDelete => nft_contract.can_delete(),
+        MusicNftAction::GetCapabilities => nft_contract.get_capabilities(),
+        MusicNftAction::SetUser {
+            token_id,
+            user,
+            expires,
+        } => nft_contract.set_user(token_id, user, expires),
+        MusicNftAction::RoyaltyInfo {
+            token_id,
+            sale_price,
+        } => nft_contract.royalty_info(token_id, sale_price),
+        MusicNftAction::SetTokenRoyalty { token_id, royalty } => {
+            nft_contract.set_token_royalty(token_id, royalty)
+        }
+        MusicNftAction::GetTokenCombination { .. } => Err(MusicNftError(
+            "This collection does not support token combinations".to_owned(),
+        )),
     };
 
     msg::reply(result, 0)
@@ -555,6 +768,9 @@ extern "C" fn state() {
         StateQuery::Name => StateReply::Name(nft.config.name),
         StateQuery::Description => StateReply::Description(nft.config.description),
         StateQuery::Config => StateReply::Config(nft.config),
+        StateQuery::ListenCapability { account, token_id } => {
+            StateReply::ListenCapability(nft.listen_capability(&account, token_id))
+        }
         StateQuery::All => {
             let nft_state: NftState = nft.into();
             StateReply::All(nft_state)
@@ -569,6 +785,9 @@ impl From<NftContract> for NftState {
             tokens,
             owners,
             token_approvals,
+            operator_approvals,
+            users,
+            token_royalties,
             config,
             nonce,
             links_and_data,
@@ -589,11 +808,23 @@ impl From<NftContract> for NftState {
             .iter()
             .map(|(nft_id, actor_id)| (*nft_id, *actor_id))
             .collect();
+        let operator_approvals = operator_approvals
+            .into_iter()
+            .map(|(owner, operators)| (owner, operators.into_iter().collect()))
+            .collect();
+        let users = users
+            .iter()
+            .map(|(nft_id, user)| (*nft_id, *user))
+            .collect();
+        let token_royalties = token_royalties.into_iter().collect();
 
         Self {
             tokens,
             owners,
             token_approvals,
+            operator_approvals,
+            users,
+            token_royalties,
             config,
             nonce,
             links_and_data,
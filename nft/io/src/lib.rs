@@ -2,6 +2,12 @@
 
 use gmeta::{In, InOut, Metadata};
 use gstd::{prelude::*, ActorId};
+
+pub use common_io::{
+    royalty_recipients_are_valid, scale_royalty_recipients, Capabilities, RoyaltyInfo,
+    PROTOCOL_VERSION,
+};
+
 pub type NftId = u64;
 pub type TimeSec = u32;
 
@@ -49,6 +55,7 @@ pub struct Config {
     pub user_mint_limit: Option<u32>,
     pub additional_links: Option<AdditionalLinks>,
     pub royalty: u16,
+    pub royalty_recipients: Vec<(ActorId, u16)>,
     pub payment_for_mint: u128,
     pub transferable: Option<u64>,
     pub sellable: Option<u64>,
@@ -78,6 +85,23 @@ pub enum NftAction {
         token_id: NftId,
     },
     CanDelete,
+    GetTokenCombination {
+        token_id: NftId,
+    },
+    SetUser {
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfo {
+        token_id: NftId,
+        sale_price: u128,
+    },
+    IsApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+    },
+    GetCapabilities,
     Mint,
     Approve {
         to: ActorId,
@@ -86,6 +110,10 @@ pub enum NftAction {
     RevokeApproval {
         token_id: NftId,
     },
+    ApproveForAll {
+        operator: ActorId,
+        approved: bool,
+    },
     Expand {
         additional_links: Vec<(String, ImageData)>,
     },
@@ -100,6 +128,10 @@ pub enum NftAction {
         token_id: NftId,
         metadata: String,
     },
+    SetTokenRoyalty {
+        token_id: NftId,
+        royalty: Option<u16>,
+    },
 }
 
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
@@ -115,8 +147,24 @@ pub enum NftEvent {
         sellable: bool,
         collection_owner: ActorId,
         royalty: u16,
+        user: Option<(ActorId, u64)>,
+        royalty_recipients: Vec<(ActorId, u16)>,
     },
     CanDelete(bool),
+    TokenCombinationReceived {
+        combination: Vec<u8>,
+    },
+    UserSet {
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    },
+    RoyaltyInfoReceived(RoyaltyInfo),
+    IsApprovedForAll(bool),
+    CapabilitiesReceived {
+        capabilities: Capabilities,
+        collection_owner: ActorId,
+    },
     Initialized {
         config: Config,
     },
@@ -131,6 +179,11 @@ pub enum NftEvent {
     ApprovalRevoked {
         token_id: NftId,
     },
+    ApprovedForAll {
+        owner: ActorId,
+        operator: ActorId,
+        approved: bool,
+    },
     Expanded {
         additional_links: Vec<(String, ImageData)>,
     },
@@ -145,6 +198,10 @@ pub enum NftEvent {
         token_id: NftId,
         metadata: String,
     },
+    TokenRoyaltySet {
+        token_id: NftId,
+        royalty: Option<u16>,
+    },
 }
 #[derive(Debug, Clone, Encode, Decode, TypeInfo)]
 pub struct NftError(pub String);
@@ -154,6 +211,9 @@ pub struct NftState {
     pub tokens: Vec<(NftId, Nft)>,
     pub owners: Vec<(ActorId, Vec<NftId>)>,
     pub token_approvals: Vec<(NftId, ActorId)>,
+    pub operator_approvals: Vec<(ActorId, Vec<ActorId>)>,
+    pub users: Vec<(NftId, (ActorId, u64))>,
+    pub token_royalties: Vec<(NftId, u16)>,
     pub config: Config,
     pub nonce: NftId,
     pub img_links_and_data: Vec<(String, ImageData)>,
@@ -6,8 +6,10 @@ use gstd::{
     ActorId,
 };
 use nft_io::{
-    Action, Config, ImageData, Nft, NftAction, NftError, NftEvent, NftId, NftInit, NftState,
+    royalty_recipients_are_valid, scale_royalty_recipients, Action, Capabilities, Config,
+    ImageData, Nft, NftAction, NftError, NftEvent, NftId, NftInit, NftState, RoyaltyInfo,
     StateQuery, StateReply, BLOCK_DURATION_IN_SECS, EXISTENTIAL_DEPOSIT, GAS_AUTO_CHANGING,
+    PROTOCOL_VERSION,
 };
 
 #[derive(Debug)]
@@ -16,6 +18,12 @@ struct NftContract {
     pub owners: HashMap<ActorId, HashSet<NftId>>,
     pub restriction_mint: HashMap<ActorId, u32>, // made in order to track the number of mint!(not transfer) nft
     pub token_approvals: HashMap<NftId, ActorId>,
+    // operators approved by the owner to transfer all of the owner's tokens
+    pub operator_approvals: HashMap<ActorId, HashSet<ActorId>>,
+    // user of the token and the time (in ms) until which the user's rights are valid
+    pub users: HashMap<NftId, (ActorId, u64)>,
+    // royalty of the tokens that differs from the royalty of the collection
+    pub token_royalties: HashMap<NftId, u16>,
     pub config: Config,
     pub nonce: NftId,
     pub img_links_and_data: Vec<(String, ImageData)>,
@@ -135,6 +143,10 @@ impl NftContract {
             .or_insert_with(|| HashSet::from([token_id]));
 
         self.token_approvals.remove(&token_id);
+        // the rights of an active user survive the transfer until they expire
+        if self.active_user(token_id).is_none() {
+            self.users.remove(&token_id);
+        }
 
         Ok(NftEvent::Transferred {
             owner: *from,
@@ -167,6 +179,40 @@ impl NftContract {
         Ok(NftEvent::ApprovalRevoked { token_id })
     }
 
+    fn approve_for_all(
+        &mut self,
+        operator: &ActorId,
+        approved: bool,
+    ) -> Result<NftEvent, NftError> {
+        let owner = msg::source();
+        if owner == *operator {
+            return Err(NftError("Self approval is not allowed".to_owned()));
+        }
+        if approved {
+            self.operator_approvals
+                .entry(owner)
+                .or_default()
+                .insert(*operator);
+        } else if let Some(operators) = self.operator_approvals.get_mut(&owner) {
+            operators.remove(operator);
+            if operators.is_empty() {
+                self.operator_approvals.remove(&owner);
+            }
+        }
+
+        Ok(NftEvent::ApprovedForAll {
+            owner,
+            operator: *operator,
+            approved,
+        })
+    }
+
+    fn is_approved_for_all(&self, owner: &ActorId, operator: &ActorId) -> bool {
+        self.operator_approvals
+            .get(owner)
+            .map_or(false, |operators| operators.contains(operator))
+    }
+
     fn get_token_info(&self, token_id: NftId) -> Result<NftEvent, NftError> {
         let nft = self.tokens.get(&token_id);
         let (token_owner, can_sell) = if let Some(nft) = nft {
@@ -182,15 +228,123 @@ impl NftContract {
             ));
         };
         let approval = self.token_approvals.get(&token_id).copied();
+        let (royalty, royalty_recipients) = self.token_royalty(token_id);
 
         Ok(NftEvent::TokenInfoReceived {
             token_owner,
             approval,
             sellable: can_sell,
             collection_owner: self.collection_owner,
-            royalty: self.config.royalty,
+            royalty,
+            user: self.active_user(token_id),
+            royalty_recipients,
         })
     }
+    fn set_user(
+        &mut self,
+        token_id: NftId,
+        user: ActorId,
+        expires: u64,
+    ) -> Result<NftEvent, NftError> {
+        if let Some(nft) = self.tokens.get(&token_id) {
+            let msg_src = msg::source();
+            if nft.owner != msg_src && !self.is_approved_for_all(&nft.owner, &msg_src) {
+                self.check_approve(&msg_src, &token_id)?;
+            }
+        } else {
+            return Err(NftError(
+                "NonFungibleToken: token does not exist".to_owned(),
+            ));
+        }
+        if expires <= exec::block_timestamp() {
+            return Err(NftError(
+                "The expiration time must be in the future".to_owned(),
+            ));
+        }
+        if matches!(self.active_user(token_id), Some((current, _)) if current != user) {
+            return Err(NftError(
+                "The token is already used by another user".to_owned(),
+            ));
+        }
+        self.users.insert(token_id, (user, expires));
+
+        Ok(NftEvent::UserSet {
+            token_id,
+            user,
+            expires,
+        })
+    }
+    fn active_user(&self, token_id: NftId) -> Option<(ActorId, u64)> {
+        self.users
+            .get(&token_id)
+            .filter(|(_, expires)| *expires > exec::block_timestamp())
+            .copied()
+    }
+    fn set_token_royalty(
+        &mut self,
+        token_id: NftId,
+        royalty: Option<u16>,
+    ) -> Result<NftEvent, NftError> {
+        self.check_collection_owner()?;
+        let token_owner = if let Some(nft) = self.tokens.get(&token_id) {
+            nft.owner
+        } else {
+            return Err(NftError(
+                "NonFungibleToken: token does not exist".to_owned(),
+            ));
+        };
+        // made 10_000 so you can enter hundredths of a percent.
+        if royalty.map_or(false, |royalty| royalty > 10_000) {
+            return Err(NftError(
+                "Royalty percent must be less than 100%".to_owned(),
+            ));
+        }
+        // the holder of the token has agreed to the current royalty,
+        // so it can't be raised on the token that the collection owner has given away
+        let new_royalty = royalty.unwrap_or(self.config.royalty);
+        if token_owner != self.collection_owner && new_royalty > self.token_royalty(token_id).0 {
+            return Err(NftError(
+                "The royalty of the token held by another account can only be lowered".to_owned(),
+            ));
+        }
+        if let Some(royalty) = royalty {
+            self.token_royalties.insert(token_id, royalty);
+        } else {
+            self.token_royalties.remove(&token_id);
+        }
+
+        Ok(NftEvent::TokenRoyaltySet { token_id, royalty })
+    }
+    /// The royalty of the token and the shares of the royalty recipients,
+    /// the shares are scaled to the royalty of the token if it is overridden
+    fn token_royalty(&self, token_id: NftId) -> (u16, Vec<(ActorId, u16)>) {
+        match self.token_royalties.get(&token_id) {
+            Some(&royalty) => (
+                royalty,
+                scale_royalty_recipients(
+                    self.config.royalty,
+                    &self.config.royalty_recipients,
+                    royalty,
+                ),
+            ),
+            None => (self.config.royalty, self.config.royalty_recipients.clone()),
+        }
+    }
+    fn royalty_info(&self, token_id: NftId, sale_price: u128) -> Result<NftEvent, NftError> {
+        if !self.tokens.contains_key(&token_id) {
+            return Err(NftError(
+                "NonFungibleToken: token does not exist".to_owned(),
+            ));
+        }
+        let (royalty, royalty_recipients) = self.token_royalty(token_id);
+
+        Ok(NftEvent::RoyaltyInfoReceived(RoyaltyInfo::new(
+            self.collection_owner,
+            royalty,
+            &royalty_recipients,
+            sale_price,
+        )))
+    }
     fn expand(&mut self, additional_links: Vec<(String, ImageData)>) -> Result<NftEvent, NftError> {
         self.check_collection_owner()?;
         if additional_links
@@ -226,6 +380,11 @@ impl NftContract {
                 "Royalty percent must be less than 100%".to_owned(),
             ));
         }
+        if !royalty_recipients_are_valid(config.royalty, &config.royalty_recipients) {
+            return Err(NftError(
+                "The shares of the royalty recipients must add up to the royalty".to_owned(),
+            ));
+        }
         if config.transferable.is_none() && config.sellable.is_some() {
             return Err(NftError("Tokens must be transferable".to_owned()));
         }
@@ -286,6 +445,17 @@ impl NftContract {
         Ok(NftEvent::MetadataAdded { token_id, metadata })
     }
 
+    fn get_capabilities(&self) -> Result<NftEvent, NftError> {
+        Ok(NftEvent::CapabilitiesReceived {
+            capabilities: Capabilities {
+                version: PROTOCOL_VERSION,
+                operator_approvals: true,
+                rentals: true,
+                token_combinations: false,
+            },
+            collection_owner: self.collection_owner,
+        })
+    }
     fn can_delete(&self) -> Result<NftEvent, NftError> {
         Ok(NftEvent::CanDelete(self.tokens.is_empty()))
     }
@@ -368,7 +538,7 @@ impl NftContract {
             }
             let msg_src = msg::source();
             // if the owner of the token does not match the sender of the message, then check the approval
-            if owner != msg_src {
+            if owner != msg_src && !self.is_approved_for_all(&owner, &msg_src) {
                 self.check_approve(&msg_src, token_id)?;
             }
             if let Some(time) = self.config.transferable {
@@ -427,6 +597,10 @@ extern "C" fn init() {
     if config.royalty > 10_000 {
         panic!("Royalty percent must be less than 100%");
     }
+    assert!(
+        royalty_recipients_are_valid(config.royalty, &config.royalty_recipients),
+        "The shares of the royalty recipients must add up to the royalty"
+    );
     // can't be made sellable but not transferable.
     if config.transferable.is_none() && config.sellable.is_some() {
         panic!("Tokens must be transferable");
@@ -451,6 +625,9 @@ extern "C" fn init() {
             tokens: HashMap::new(),
             owners: HashMap::new(),
             token_approvals: HashMap::new(),
+            operator_approvals: HashMap::new(),
+            users: HashMap::new(),
+            token_royalties: HashMap::new(),
             restriction_mint: HashMap::new(),
             config: config.clone(),
             nonce: 0,
@@ -490,10 +667,32 @@ extern "C" fn handle() {
         }
         NftAction::Approve { to, token_id } => nft_contract.approve(&to, token_id),
         NftAction::RevokeApproval { token_id } => nft_contract.revoke_approve(token_id),
+        NftAction::ApproveForAll { operator, approved } => {
+            nft_contract.approve_for_all(&operator, approved)
+        }
+        NftAction::IsApprovedForAll { owner, operator } => Ok(NftEvent::IsApprovedForAll(
+            nft_contract.is_approved_for_all(&owner, &operator),
+        )),
         NftAction::Expand { additional_links } => nft_contract.expand(additional_links),
         NftAction::ChangeConfig { config } => nft_contract.change_config(config),
         NftAction::GetTokenInfo { token_id } => nft_contract.get_token_info(token_id),
         NftAction::CanDelete => nft_contract.can_delete(),
+        NftAction::GetCapabilities => nft_contract.get_capabilities(),
+        NftAction::SetUser {
+            token_id,
+            user,
+            expires,
+        } => nft_contract.set_user(token_id, user, expires),
+        NftAction::RoyaltyInfo {
+            token_id,
+            sale_price,
+        } => nft_contract.royalty_info(token_id, sale_price),
+        NftAction::SetTokenRoyalty { token_id, royalty } => {
+            nft_contract.set_token_royalty(token_id, royalty)
+        }
+        NftAction::GetTokenCombination { .. } => Err(NftError(
+            "This collection does not support token combinations".to_owned(),
+        )),
         NftAction::ChangeImg { token_id, img_link } => {
             nft_contract.change_image(token_id, img_link)
         }
@@ -531,6 +730,9 @@ impl From<NftContract> for NftState {
             tokens,
             owners,
             token_approvals,
+            operator_approvals,
+            users,
+            token_royalties,
             config,
             nonce,
             img_links_and_data,
@@ -551,11 +753,23 @@ impl From<NftContract> for NftState {
             .iter()
             .map(|(nft_id, actor_id)| (*nft_id, *actor_id))
             .collect();
+        let operator_approvals = operator_approvals
+            .into_iter()
+            .map(|(owner, operators)| (owner, operators.into_iter().collect()))
+            .collect();
+        let users = users
+            .iter()
+            .map(|(nft_id, user)| (*nft_id, *user))
+            .collect();
+        let token_royalties = token_royalties.into_iter().collect();
 
         Self {
             tokens,
             owners,
             token_approvals,
+            operator_approvals,
+            users,
+            token_royalties,
             config,
             nonce,
             img_links_and_data,
@@ -36,45 +36,26 @@ impl NftMarketplace {
             )));
         }
 
-        // check token info
+        // check token info and transfer the token to the marketplace
         let address_marketplace = exec::program_id();
         let msg_src = msg::source();
-        let (collection_owner, royalty) = check_token_info(
-            &collection_address,
-            token_id,
-            self.config.gas_for_get_token_info,
-            &msg_src,
-            &address_marketplace,
-        )
-        .await?;
+        let royalty_recipients = self
+            .take_token(&collection_address, token_id, &msg_src)
+            .await?;
 
-        if let NftEvent::Transferred {
-            owner: _,
-            recipient: _,
-            token_id,
-        } = transfer_from_token(
-            collection_address,
-            msg_src,
-            address_marketplace,
-            token_id,
-            self.config.gas_for_transfer_token,
-        )
-        .await?
-        {
-            self.auctions
-                .entry((collection_address, token_id))
-                .or_insert(Auction {
-                    owner: msg_src,
-                    started_at: exec::block_timestamp(),
-                    ended_at: exec::block_timestamp() + duration_ms as u64,
-                    current_price: min_price,
-                    current_winner: ActorId::zero(),
-                    collection_owner,
-                    royalty,
-                });
-        } else {
-            return Err(NftMarketplaceError("Wrong received reply".to_owned()));
-        }
+        self.auctions
+            .entry((collection_address, token_id))
+            .or_insert(Auction {
+                owner: msg_src,
+                started_at: exec::block_timestamp(),
+                ended_at: exec::block_timestamp() + duration_ms as u64,
+                current_price: min_price,
+                current_winner: ActorId::zero(),
+                recipient: None,
+                referrer: None,
+                royalty_recipients,
+                referral_rate: None,
+            });
 
         msg::send_with_gas_delayed(
             address_marketplace,
@@ -100,9 +81,16 @@ impl NftMarketplace {
         &mut self,
         collection_address: ActorId,
         token_id: u64,
+        recipient: Option<ActorId>,
+        referrer: Option<ActorId>,
     ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
         let msg_value = msg::value();
         let auction = self.check_auction(&collection_address, &token_id, &msg_value)?;
+        if let Err(error) = check_referrer(referrer, &msg::source(), recipient, &auction.owner) {
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(msg::source(), "", 0, msg_value).expect("Error in sending value");
+            return Err(error);
+        }
 
         if auction.current_winner != ActorId::zero() {
             // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
@@ -110,6 +98,8 @@ impl NftMarketplace {
                 .expect("Error in sending value");
         }
         auction.current_winner = msg::source();
+        auction.recipient = recipient;
+        auction.referrer = referrer;
         auction.current_price = msg_value;
 
         Ok(NftMarketplaceEvent::BidAdded {
@@ -131,14 +121,14 @@ impl NftMarketplace {
             ));
         }
 
-        let (price, current_owner) =
+        let (price, current_owner, referral) =
             if let Some(auction) = self.auctions.get(&(collection_address, token_id)) {
                 if auction.ended_at > exec::block_timestamp() {
                     return Err(NftMarketplaceError(
                         "The auction must not end before the deadline".to_owned(),
                     ));
                 }
-                if auction.current_winner == ActorId::zero() {
+                let referral = if auction.current_winner == ActorId::zero() {
                     transfer_token(
                         collection_address,
                         auction.owner,
@@ -146,27 +136,37 @@ impl NftMarketplace {
                         self.config.gas_for_transfer_token,
                     )
                     .await?;
+                    None
                 } else {
                     transfer_token(
                         collection_address,
-                        auction.current_winner,
+                        auction.recipient.unwrap_or(auction.current_winner),
                         token_id,
                         self.config.gas_for_transfer_token,
                     )
                     .await?;
 
-                    // transfer value to buyer and percent to collection creator
-                    currency_transfer(
-                        auction.collection_owner,
+                    // transfer the referral fee to the referrer, the rest of the value to buyer
+                    // and percent to collection creator
+                    let referral = referral_transfer(
+                        auction.referrer,
+                        auction.current_price,
+                        auction.referral_rate,
+                        self.config.minimum_transfer_value,
+                    );
+                    currency_transfer_after_referral(
+                        &auction.royalty_recipients,
                         auction.owner,
                         auction.current_price,
-                        auction.royalty,
+                        referral,
                         self.config.minimum_transfer_value,
+                        self.gas_for_token_owner(&auction.owner),
                     );
-                }
+                    referral
+                };
                 let price = auction.current_price;
-                let current_owner = auction.current_winner;
-                (price, current_owner)
+                let current_owner = auction.recipient.unwrap_or(auction.current_winner);
+                (price, current_owner, referral)
             } else {
                 return Err(NftMarketplaceError("There is no such auction".to_owned()));
             };
@@ -180,6 +180,7 @@ impl NftMarketplace {
             token_id,
             price,
             current_owner,
+            referral,
         })
     }
 
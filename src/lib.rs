@@ -1,20 +1,37 @@
 #![no_std]
 
+use core::cmp::Reverse;
 use gstd::{
-    collections::HashMap, debug, exec, msg, prelude::*, prog::ProgramGenerator, ActorId, CodeId,
+    collections::{BTreeSet, HashMap, HashSet},
+    debug, exec, msg,
+    prelude::*,
+    prog::ProgramGenerator,
+    ActorId, CodeId,
 };
 use nft_marketplace_io::*;
 
 mod auction;
+mod loan;
 mod nft_messages;
 mod offer;
+mod order_book;
 mod payment;
+mod raffle;
+mod rental;
 mod sale;
+mod signed_order;
+mod swap;
+mod vault;
+
+use nft_messages::{get_capabilities, get_collection_owner};
+use payment::refund_on_error;
 
 type CollectionId = ActorId;
 type TokenId = u64;
 type Price = u128;
 type TypeName = String;
+type SwapId = u64;
+type LoanId = u64;
 
 #[derive(Default)]
 pub struct NftMarketplace {
@@ -24,7 +41,32 @@ pub struct NftMarketplace {
     pub type_collections: HashMap<String, TypeCollectionInfo>,
     pub sales: HashMap<(CollectionId, TokenId), NftInfoForSale>,
     pub auctions: HashMap<(CollectionId, TokenId), Auction>,
-    pub offers: HashMap<Offer, Price>,
+    pub offers: HashMap<Offer, OfferInfo>,
+    pub counter_offers: HashMap<Offer, (ActorId, Price)>,
+    pub collection_offers: HashMap<CollectionOffer, CollectionOfferInfo>,
+    // tokens on sale and collection offers of each collection sorted by price, the order book
+    pub ask_index: HashMap<CollectionId, BTreeSet<(Price, TokenId)>>,
+    pub bid_index: HashMap<CollectionId, BTreeSet<(Reverse<Price>, ActorId)>>,
+    pub trait_offers: HashMap<TraitOffer, Price>,
+    pub swaps: HashMap<SwapId, Swap>,
+    pub swap_nonce: SwapId,
+    // escrowed tokens that the marketplace failed to transfer to their recipients
+    pub unreleased_tokens: HashMap<(CollectionId, TokenId), ActorId>,
+    pub rentals: HashMap<(CollectionId, TokenId), Rental>,
+    pub loans: HashMap<LoanId, Loan>,
+    pub loan_nonce: LoanId,
+    pub vault_code_id: Option<CodeId>,
+    pub vaults: HashMap<ActorId, (CollectionId, TokenId)>,
+    pub raffles: HashMap<(CollectionId, TokenId), Raffle>,
+    pub used_order_nonces: HashSet<(ActorId, u64)>,
+    // features that the collections reported in the handshake when they were created
+    pub collection_capabilities: HashMap<CollectionId, Capabilities>,
+    // collections that were deployed outside the marketplace and registered afterwards
+    pub imported_collections: HashSet<CollectionId>,
+    // versions of the collection types that the collections were created with
+    pub collection_type_versions: HashMap<CollectionId, u32>,
+    // collections that were deployed, but failed the handshake, so they can't be traded
+    pub unsupported_collections: HashMap<CollectionId, (TypeName, ActorId)>,
     pub config: Config,
 }
 
@@ -38,6 +80,7 @@ extern "C" fn init() {
         gas_for_close_auction,
         gas_for_delete_collection,
         gas_for_get_token_info,
+        gas_for_cleanup_offers,
         time_between_create_collections,
         minimum_transfer_value,
         ms_in_block,
@@ -51,6 +94,7 @@ extern "C" fn init() {
             gas_for_close_auction,
             gas_for_delete_collection,
             gas_for_get_token_info,
+            gas_for_cleanup_offers,
             time_between_create_collections,
             minimum_transfer_value,
             ms_in_block,
@@ -76,16 +120,39 @@ async fn main() {
             type_name,
             type_description,
         } => nft_marketplace.add_new_collection(code_id, meta_link, type_name, type_description),
+        NftMarketplaceAction::UpdateCollectionType {
+            type_name,
+            code_id,
+            meta_link,
+        } => nft_marketplace.update_collection_type(type_name, code_id, meta_link),
+        NftMarketplaceAction::DeprecateCollectionType { type_name } => {
+            nft_marketplace.deprecate_collection_type(type_name)
+        }
+        NftMarketplaceAction::RemoveCollectionType { type_name } => {
+            nft_marketplace.remove_collection_type(type_name)
+        }
         NftMarketplaceAction::CreateCollection { type_name, payload } => {
             nft_marketplace.create_collection(type_name, payload).await
         }
+        NftMarketplaceAction::RegisterExternalCollection {
+            address,
+            type_name,
+            token_id,
+        } => {
+            nft_marketplace
+                .register_external_collection(address, type_name, token_id)
+                .await
+        }
         NftMarketplaceAction::SaleNft {
             collection_address,
             token_id,
             price,
         } => {
+            let result = nft_marketplace
+                .sell(collection_address, token_id, price, true)
+                .await;
             nft_marketplace
-                .sell(collection_address, token_id, price)
+                .check_vault_listing(collection_address, token_id, result)
                 .await
         }
         NftMarketplaceAction::CancelSaleNft {
@@ -99,21 +166,32 @@ async fn main() {
         NftMarketplaceAction::BuyNft {
             collection_address,
             token_id,
-        } => nft_marketplace.buy(collection_address, token_id).await,
+            recipient,
+            referrer,
+        } => {
+            nft_marketplace
+                .buy(collection_address, token_id, recipient, referrer)
+                .await
+        }
         NftMarketplaceAction::CreateAuction {
             collection_address,
             token_id,
             min_price,
             duration_ms,
         } => {
-            nft_marketplace
+            let result = nft_marketplace
                 .create_auction(collection_address, token_id, min_price, duration_ms)
+                .await;
+            nft_marketplace
+                .check_vault_listing(collection_address, token_id, result)
                 .await
         }
         NftMarketplaceAction::AddBid {
             collection_address,
             token_id,
-        } => nft_marketplace.add_bid(collection_address, token_id),
+            recipient,
+            referrer,
+        } => nft_marketplace.add_bid(collection_address, token_id, recipient, referrer),
         NftMarketplaceAction::CloseAuction {
             collection_address,
             token_id,
@@ -133,16 +211,221 @@ async fn main() {
         NftMarketplaceAction::CreateOffer {
             collection_address,
             token_id,
-        } => {
+            expires_at,
+            recipient,
+            referrer,
+        } => refund_on_error(
             nft_marketplace
-                .create_offer(collection_address, token_id)
-                .await
-        }
+                .create_offer(
+                    collection_address,
+                    token_id,
+                    expires_at,
+                    recipient,
+                    referrer,
+                )
+                .await,
+        ),
         NftMarketplaceAction::CancelOffer {
             collection_address,
             token_id,
         } => nft_marketplace.cancel_offer(collection_address, token_id),
         NftMarketplaceAction::AcceptOffer { offer } => nft_marketplace.accept_offer(offer).await,
+        NftMarketplaceAction::CleanupExpiredOffers { offers } => {
+            nft_marketplace.cleanup_expired_offers(offers)
+        }
+        NftMarketplaceAction::CounterOffer { offer, price } => {
+            nft_marketplace.counter_offer(offer, price).await
+        }
+        NftMarketplaceAction::AcceptCounterOffer { offer } => {
+            refund_on_error(nft_marketplace.accept_counter_offer(offer).await)
+        }
+        NftMarketplaceAction::CreateCollectionOffer {
+            collection_address,
+            quantity,
+        } => refund_on_error(
+            nft_marketplace
+                .create_collection_offer(collection_address, quantity)
+                .await,
+        ),
+        NftMarketplaceAction::CancelCollectionOffer { collection_address } => {
+            nft_marketplace.cancel_collection_offer(collection_address)
+        }
+        NftMarketplaceAction::AcceptCollectionOffer { offer, token_id } => {
+            nft_marketplace
+                .accept_collection_offer(offer, token_id)
+                .await
+        }
+        NftMarketplaceAction::CreateTraitOffer {
+            collection_address,
+            layer,
+            option,
+        } => refund_on_error(nft_marketplace.create_trait_offer(collection_address, layer, option)),
+        NftMarketplaceAction::CancelTraitOffer {
+            collection_address,
+            layer,
+            option,
+        } => nft_marketplace.cancel_trait_offer(collection_address, layer, option),
+        NftMarketplaceAction::AcceptTraitOffer { offer, token_id } => {
+            nft_marketplace.accept_trait_offer(offer, token_id).await
+        }
+        NftMarketplaceAction::ProposeSwap {
+            counterparty,
+            proposer_items,
+            counterparty_items,
+            expires_at,
+        } => refund_on_error(
+            nft_marketplace
+                .propose_swap(counterparty, proposer_items, counterparty_items, expires_at)
+                .await,
+        ),
+        NftMarketplaceAction::AcceptSwap { swap_id } => nft_marketplace.accept_swap(swap_id).await,
+        NftMarketplaceAction::CancelSwap { swap_id } => nft_marketplace.cancel_swap(swap_id).await,
+        NftMarketplaceAction::ExpireSwap { swap_id } => nft_marketplace.expire_swap(swap_id).await,
+        NftMarketplaceAction::ReleaseToken {
+            collection_address,
+            token_id,
+        } => {
+            nft_marketplace
+                .release_token(collection_address, token_id)
+                .await
+        }
+        NftMarketplaceAction::ListForRent {
+            collection_address,
+            token_id,
+            price_per_day,
+            max_days,
+        } => {
+            nft_marketplace
+                .list_for_rent(collection_address, token_id, price_per_day, max_days)
+                .await
+        }
+        NftMarketplaceAction::Rent {
+            collection_address,
+            token_id,
+            days,
+        } => refund_on_error(
+            nft_marketplace
+                .rent(collection_address, token_id, days)
+                .await,
+        ),
+        NftMarketplaceAction::CancelRentListing {
+            collection_address,
+            token_id,
+        } => nft_marketplace.cancel_rent_listing(collection_address, token_id),
+        NftMarketplaceAction::RequestLoan {
+            collection_address,
+            token_id,
+            amount,
+            interest,
+            duration_ms,
+        } => {
+            nft_marketplace
+                .request_loan(collection_address, token_id, amount, interest, duration_ms)
+                .await
+        }
+        NftMarketplaceAction::CancelLoanRequest { loan_id } => {
+            nft_marketplace.cancel_loan_request(loan_id).await
+        }
+        NftMarketplaceAction::FundLoan { loan_id } => {
+            refund_on_error(nft_marketplace.fund_loan(loan_id))
+        }
+        NftMarketplaceAction::RepayLoan { loan_id } => {
+            refund_on_error(nft_marketplace.repay_loan(loan_id).await)
+        }
+        NftMarketplaceAction::ClaimCollateral { loan_id } => {
+            nft_marketplace.claim_collateral(loan_id).await
+        }
+        NftMarketplaceAction::SetVaultCode { code_id } => nft_marketplace.set_vault_code(code_id),
+        NftMarketplaceAction::CreateVault {
+            collection_address,
+            token_id,
+            total_shares,
+            reserve_price,
+            buyout_duration_ms,
+        } => {
+            nft_marketplace
+                .create_vault(
+                    collection_address,
+                    token_id,
+                    total_shares,
+                    reserve_price,
+                    buyout_duration_ms,
+                )
+                .await
+        }
+        NftMarketplaceAction::CloseVault => nft_marketplace.close_vault(),
+        NftMarketplaceAction::CreateRaffle {
+            collection_address,
+            token_id,
+            ticket_price,
+            max_tickets,
+            min_tickets,
+            ends_at,
+        } => {
+            nft_marketplace
+                .create_raffle(
+                    collection_address,
+                    token_id,
+                    ticket_price,
+                    max_tickets,
+                    min_tickets,
+                    ends_at,
+                )
+                .await
+        }
+        NftMarketplaceAction::BuyTickets {
+            collection_address,
+            token_id,
+            count,
+        } => refund_on_error(nft_marketplace.buy_tickets(collection_address, token_id, count)),
+        NftMarketplaceAction::DrawRaffle {
+            collection_address,
+            token_id,
+        } => {
+            nft_marketplace
+                .draw_raffle(collection_address, token_id)
+                .await
+        }
+        NftMarketplaceAction::BuyMany { items, max_total } => {
+            refund_on_error(nft_marketplace.buy_many(items, max_total).await)
+        }
+        NftMarketplaceAction::BuyCheapest {
+            collection_address,
+            count,
+            max_total,
+        } => refund_on_error(
+            nft_marketplace
+                .buy_cheapest(collection_address, count, max_total)
+                .await,
+        ),
+        NftMarketplaceAction::SetReferralRate {
+            collection_address,
+            token_id,
+            referral_rate,
+        } => nft_marketplace.set_referral_rate(collection_address, token_id, referral_rate),
+        NftMarketplaceAction::BuySignedOrder { order, signature } => {
+            refund_on_error(nft_marketplace.buy_signed_order(order, signature).await)
+        }
+        NftMarketplaceAction::CancelOrderNonce { nonce } => {
+            nft_marketplace.cancel_order_nonce(nonce)
+        }
+        NftMarketplaceAction::SaleNftNonCustodial {
+            collection_address,
+            token_id,
+            price,
+        } => {
+            nft_marketplace
+                .sell(collection_address, token_id, price, false)
+                .await
+        }
+        NftMarketplaceAction::InvalidateListing {
+            collection_address,
+            token_id,
+        } => {
+            nft_marketplace
+                .invalidate_listing(collection_address, token_id)
+                .await
+        }
         NftMarketplaceAction::DeleteCollection { collection_address } => {
             nft_marketplace.delete_collection(collection_address).await
         }
@@ -154,6 +437,7 @@ async fn main() {
             gas_for_close_auction,
             gas_for_delete_collection,
             gas_for_get_token_info,
+            gas_for_cleanup_offers,
             time_between_create_collections,
             minimum_transfer_value,
             ms_in_block,
@@ -163,6 +447,7 @@ async fn main() {
             gas_for_close_auction,
             gas_for_delete_collection,
             gas_for_get_token_info,
+            gas_for_cleanup_offers,
             time_between_create_collections,
             minimum_transfer_value,
             ms_in_block,
@@ -175,6 +460,8 @@ async fn main() {
 }
 
 impl NftMarketplace {
+    /// The handshake needs a deployed program, so it can't run for a code id,
+    /// every collection created from the code runs it in `create_collection` instead
     pub fn add_new_collection(
         &mut self,
         code_id: CodeId,
@@ -183,11 +470,19 @@ impl NftMarketplace {
         type_description: String,
     ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
         self.check_admin()?;
+        if self.type_collections.contains_key(&type_name) {
+            return Err(NftMarketplaceError(
+                "This collection type already exists".to_owned(),
+            ));
+        }
 
         let collection_info = TypeCollectionInfo {
             code_id,
             meta_link: meta_link.clone(),
             type_description: type_description.clone(),
+            version: 1,
+            previous_versions: Vec::new(),
+            deprecated: false,
         };
         self.type_collections
             .insert(type_name.clone(), collection_info.clone());
@@ -200,6 +495,62 @@ impl NftMarketplace {
         })
     }
 
+    /// The collections that were created with the previous code keep their version
+    pub fn update_collection_type(
+        &mut self,
+        type_name: String,
+        code_id: CodeId,
+        meta_link: String,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        self.check_admin()?;
+        let collection_info = self.get_collection_info_mut(&type_name)?;
+
+        collection_info
+            .previous_versions
+            .push((collection_info.code_id, collection_info.meta_link.clone()));
+        collection_info.code_id = code_id;
+        collection_info.meta_link = meta_link.clone();
+        collection_info.version += 1;
+
+        Ok(NftMarketplaceEvent::CollectionTypeUpdated {
+            type_name,
+            code_id,
+            meta_link,
+            version: collection_info.version,
+        })
+    }
+
+    pub fn deprecate_collection_type(
+        &mut self,
+        type_name: String,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        self.check_admin()?;
+        self.get_collection_info_mut(&type_name)?.deprecated = true;
+
+        Ok(NftMarketplaceEvent::CollectionTypeDeprecated { type_name })
+    }
+
+    /// A type can only be removed when there are no collections of this type left
+    pub fn remove_collection_type(
+        &mut self,
+        type_name: String,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        self.check_admin()?;
+        self.get_collection_info(&type_name)?;
+        if self
+            .collection_to_owner
+            .values()
+            .any(|(collection_type, _)| *collection_type == type_name)
+        {
+            return Err(NftMarketplaceError(
+                "There are collections of this type in the marketplace".to_owned(),
+            ));
+        }
+        self.type_collections.remove(&type_name);
+
+        Ok(NftMarketplaceEvent::CollectionTypeRemoved { type_name })
+    }
+
     pub async fn create_collection(
         &mut self,
         type_name: String,
@@ -208,9 +559,21 @@ impl NftMarketplace {
         let msg_src = msg::source();
         self.check_time_creation(&msg_src)?;
 
-        let collection_info = self.get_collection_info(&type_name)?;
+        let collection_info = self.get_active_collection_info(&type_name)?;
+        let type_version = collection_info.version;
         debug!("PAYLOAD: {:?}", payload);
 
+        // the init payload of every collection starts with the collection owner,
+        // so the collection of another owner is rejected before it is deployed
+        let payload_owner = ActorId::decode(&mut payload.as_slice()).map_err(|_| {
+            NftMarketplaceError("The init payload must start with the collection owner".to_owned())
+        })?;
+        if payload_owner != msg_src {
+            return Err(NftMarketplaceError(
+                "The collection owner must be the creator of the collection".to_owned(),
+            ));
+        }
+
         let (address, _) = ProgramGenerator::create_program_bytes_with_gas_for_reply(
             collection_info.code_id,
             payload,
@@ -222,18 +585,93 @@ impl NftMarketplace {
         .await
         .expect("Program was not initialized");
 
-        self.collection_to_owner.insert(address, (type_name.clone(), msg_src));
-
         self.time_creation
             .entry(msg_src)
             .insert(exec::block_timestamp());
 
+        // the collection must speak the same protocol as the marketplace and keep the owner
+        // from the init payload, the program is already deployed, so it is recorded as unsupported
+        let capabilities =
+            match get_capabilities(&address, self.config.gas_for_get_token_info).await {
+                Ok((capabilities, collection_owner))
+                    if capabilities.version == PROTOCOL_VERSION && collection_owner == msg_src =>
+                {
+                    capabilities
+                }
+                _ => {
+                    self.unsupported_collections
+                        .insert(address, (type_name.clone(), msg_src));
+                    return Ok(NftMarketplaceEvent::UnsupportedCollectionCreated {
+                        type_name,
+                        collection_address: address,
+                    });
+                }
+            };
+
+        self.collection_to_owner
+            .insert(address, (type_name.clone(), msg_src));
+        self.collection_capabilities.insert(address, capabilities);
+        self.collection_type_versions.insert(address, type_version);
+
         Ok(NftMarketplaceEvent::CollectionCreated {
             type_name,
             collection_address: address,
         })
     }
 
+    /// The collection owner is taken from the information about the given token,
+    /// admins can register any collection, other users only their own collections
+    pub async fn register_external_collection(
+        &mut self,
+        address: ActorId,
+        type_name: String,
+        token_id: TokenId,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let msg_src = msg::source();
+        let type_version = self.get_active_collection_info(&type_name)?.version;
+        if self.collection_to_owner.contains_key(&address) {
+            return Err(NftMarketplaceError(
+                "This collection is already in the marketplace".to_owned(),
+            ));
+        }
+
+        let collection_owner =
+            get_collection_owner(&address, token_id, self.config.gas_for_get_token_info).await?;
+        if collection_owner != msg_src && !self.admins.contains(&msg_src) {
+            return Err(NftMarketplaceError(
+                "Only the owner of the collection can register it".to_owned(),
+            ));
+        }
+
+        // the collection must speak the same protocol as the marketplace
+        let (capabilities, _) =
+            get_capabilities(&address, self.config.gas_for_get_token_info).await?;
+        if capabilities.version != PROTOCOL_VERSION {
+            return Err(NftMarketplaceError(format!(
+                "The collection protocol version must be {}",
+                PROTOCOL_VERSION
+            )));
+        }
+        // the collection could have been registered while waiting for the replies
+        if self.collection_to_owner.contains_key(&address) {
+            return Err(NftMarketplaceError(
+                "This collection is already in the marketplace".to_owned(),
+            ));
+        }
+
+        self.collection_to_owner
+            .insert(address, (type_name.clone(), collection_owner));
+        self.collection_capabilities.insert(address, capabilities);
+        self.imported_collections.insert(address);
+        self.collection_type_versions.insert(address, type_version);
+
+        Ok(NftMarketplaceEvent::ExternalCollectionRegistered {
+            type_name,
+            collection_address: address,
+            owner: collection_owner,
+        })
+    }
+
     pub async fn delete_collection(
         &mut self,
         collection_address: ActorId,
@@ -278,6 +716,10 @@ impl NftMarketplace {
             ));
         }
 
+        self.collection_capabilities.remove(&collection_address);
+        self.imported_collections.remove(&collection_address);
+        self.collection_type_versions.remove(&collection_address);
+
         Ok(NftMarketplaceEvent::CollectionDeleted { collection_address })
     }
 
@@ -304,6 +746,7 @@ impl NftMarketplace {
         gas_for_close_auction: Option<u64>,
         gas_for_delete_collection: Option<u64>,
         gas_for_get_token_info: Option<u64>,
+        gas_for_cleanup_offers: Option<u64>,
         time_between_create_collections: Option<u64>,
         minimum_transfer_value: Option<u128>,
         ms_in_block: Option<u32>,
@@ -324,6 +767,9 @@ impl NftMarketplace {
         if let Some(gas) = gas_for_get_token_info {
             self.config.gas_for_get_token_info = gas;
         }
+        if let Some(gas) = gas_for_cleanup_offers {
+            self.config.gas_for_cleanup_offers = gas;
+        }
         if let Some(time) = time_between_create_collections {
             self.config.time_between_create_collections = time;
         }
@@ -340,6 +786,7 @@ impl NftMarketplace {
             gas_for_close_auction,
             gas_for_delete_collection,
             gas_for_get_token_info,
+            gas_for_cleanup_offers,
             time_between_create_collections,
             minimum_transfer_value,
             ms_in_block,
@@ -378,6 +825,14 @@ impl NftMarketplace {
         }
     }
 
+    /// Features of the collection, a collection without the handshake has none of them
+    pub fn capabilities(&self, collection_address: &ActorId) -> Capabilities {
+        self.collection_capabilities
+            .get(collection_address)
+            .copied()
+            .unwrap_or_default()
+    }
+
     fn get_collection_info(&self, type_name: &str) -> Result<&TypeCollectionInfo, NftMarketplaceError> {
         if let Some(collection_info) = self.type_collections.get(type_name) {
             Ok(collection_info)
@@ -387,6 +842,33 @@ impl NftMarketplace {
             ))
         }
     }
+
+    fn get_collection_info_mut(
+        &mut self,
+        type_name: &str,
+    ) -> Result<&mut TypeCollectionInfo, NftMarketplaceError> {
+        if let Some(collection_info) = self.type_collections.get_mut(type_name) {
+            Ok(collection_info)
+        } else {
+            Err(NftMarketplaceError(
+                "There is no collection with this name yet.".to_owned(),
+            ))
+        }
+    }
+
+    /// New collections can only be made of the types that are not deprecated
+    fn get_active_collection_info(
+        &self,
+        type_name: &str,
+    ) -> Result<&TypeCollectionInfo, NftMarketplaceError> {
+        let collection_info = self.get_collection_info(type_name)?;
+        if collection_info.deprecated {
+            return Err(NftMarketplaceError(
+                "This collection type is deprecated".to_owned(),
+            ));
+        }
+        Ok(collection_info)
+    }
 }
 
 #[no_mangle]
@@ -426,11 +908,35 @@ extern "C" fn state() {
         StateQuery::GetCollectionInfo(collection_address) => {
             let collection_to_owner = nft_marketplace.collection_to_owner.get(&collection_address);
             if let Some((type_name, owner)) = collection_to_owner {
-                let meta_link = &nft_marketplace.type_collections.get(type_name).expect("This collection type name must exist").meta_link;
+                let collection_type = nft_marketplace
+                    .type_collections
+                    .get(type_name)
+                    .expect("This collection type name must exist");
+                let type_version = nft_marketplace
+                    .collection_type_versions
+                    .get(&collection_address)
+                    .copied()
+                    .unwrap_or(collection_type.version);
+                // the collection keeps the meta of the version it was created with
+                let meta_link = if type_version < collection_type.version {
+                    &collection_type.previous_versions[type_version as usize - 1].1
+                } else {
+                    &collection_type.meta_link
+                };
                 let collection_info = CollectionInfo{
                     owner: *owner,
                     type_name: type_name.clone(),
+                    type_version,
                     meta_link: meta_link.clone(),
+                    capabilities: nft_marketplace.capabilities(&collection_address),
+                    origin: if nft_marketplace
+                        .imported_collections
+                        .contains(&collection_address)
+                    {
+                        CollectionOrigin::Imported
+                    } else {
+                        CollectionOrigin::DeployedByMarketplace
+                    },
                 };
                 StateReply::CollectionInfo(Some(collection_info))
             } else {
@@ -438,6 +944,40 @@ extern "C" fn state() {
             }
            
         }   
+        StateQuery::OpenLoans => {
+            let loans = nft_marketplace
+                .loans
+                .into_iter()
+                .filter(|(_, loan)| loan.lender.is_none())
+                .collect();
+            StateReply::OpenLoans(loans)
+        }
+        StateQuery::ActiveLoans => {
+            let loans = nft_marketplace
+                .loans
+                .into_iter()
+                .filter(|(_, loan)| loan.lender.is_some())
+                .collect();
+            StateReply::ActiveLoans(loans)
+        }
+        StateQuery::BestBidAsk(collection_address) => {
+            let best_bid = nft_marketplace
+                .bids(&collection_address)
+                .next()
+                .map(|(price, _, _)| price);
+            let best_ask = nft_marketplace
+                .asks(&collection_address, None)
+                .next()
+                .map(|(price, _, _)| price);
+            StateReply::BestBidAsk { best_bid, best_ask }
+        }
+        StateQuery::OrderBookDepth {
+            collection_address,
+            depth,
+        } => {
+            let (asks, bids) = nft_marketplace.order_book_depth(&collection_address, depth);
+            StateReply::OrderBookDepth { asks, bids }
+        }
     };
     msg::reply(reply, 0).expect("Unable to share the state");
 }
@@ -452,7 +992,23 @@ impl From<NftMarketplace> for State {
             sales,
             auctions,
             offers,
+            counter_offers,
+            collection_offers,
+            trait_offers,
+            swaps,
+            unreleased_tokens,
+            rentals,
+            loans,
+            vault_code_id,
+            vaults,
+            raffles,
+            used_order_nonces,
+            collection_capabilities,
+            imported_collections,
+            collection_type_versions,
+            unsupported_collections,
             config,
+            ..
         } = value;
 
         let collection_to_owner = collection_to_owner
@@ -477,10 +1033,46 @@ impl From<NftMarketplace> for State {
             .collect();
 
         let offers = offers
+            .into_iter()
+            .map(|(offer, info)| (offer, info))
+            .collect();
+
+        let counter_offers = counter_offers
+            .into_iter()
+            .map(|(offer, counter_offer)| (offer, counter_offer))
+            .collect();
+
+        let collection_offers = collection_offers
+            .into_iter()
+            .map(|(offer, info)| (offer, info))
+            .collect();
+
+        let trait_offers = trait_offers
             .into_iter()
             .map(|(offer, price)| (offer, price))
             .collect();
 
+        let swaps = swaps.into_iter().map(|(id, swap)| (id, swap)).collect();
+        let unreleased_tokens = unreleased_tokens.into_iter().collect();
+        let rentals = rentals
+            .into_iter()
+            .map(|(id, rental)| (id, rental))
+            .collect();
+        let loans = loans.into_iter().map(|(id, loan)| (id, loan)).collect();
+        let vaults = vaults
+            .into_iter()
+            .map(|(vault, token)| (vault, token))
+            .collect();
+        let raffles = raffles
+            .into_iter()
+            .map(|(id, raffle)| (id, raffle))
+            .collect();
+        let used_order_nonces = used_order_nonces.into_iter().collect();
+        let collection_capabilities = collection_capabilities.into_iter().collect();
+        let imported_collections = imported_collections.into_iter().collect();
+        let collection_type_versions = collection_type_versions.into_iter().collect();
+        let unsupported_collections = unsupported_collections.into_iter().collect();
+
         Self {
             admins,
             collection_to_owner,
@@ -489,6 +1081,21 @@ impl From<NftMarketplace> for State {
             sales,
             auctions,
             offers,
+            counter_offers,
+            collection_offers,
+            trait_offers,
+            swaps,
+            unreleased_tokens,
+            rentals,
+            loans,
+            vault_code_id,
+            vaults,
+            raffles,
+            used_order_nonces,
+            collection_capabilities,
+            imported_collections,
+            collection_type_versions,
+            unsupported_collections,
             config,
         }
     }
@@ -0,0 +1,212 @@
+use crate::nft_messages::*;
+use crate::NftMarketplace;
+use gstd::{exec, msg, prelude::*, ActorId};
+use nft_marketplace_io::*;
+
+impl NftMarketplace {
+    pub async fn request_loan(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+        amount: u128,
+        interest: u128,
+        duration_ms: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        if !self.collection_to_owner.contains_key(&collection_address) {
+            return Err(NftMarketplaceError(
+                "This collection address is not in the marketplace".to_owned(),
+            ));
+        }
+        if amount < self.config.minimum_transfer_value {
+            return Err(NftMarketplaceError(format!(
+                "The loan amount must be greater than existential deposit ({})",
+                self.config.minimum_transfer_value
+            )));
+        }
+        if duration_ms == 0 {
+            return Err(NftMarketplaceError(
+                "The loan duration must be greater than zero".to_owned(),
+            ));
+        }
+
+        // check token info
+        let address_marketplace = exec::program_id();
+        let msg_src = msg::source();
+        check_token_info(
+            &collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            &msg_src,
+            &address_marketplace,
+            self.capabilities(&collection_address).operator_approvals,
+        )
+        .await?;
+
+        // the collateral is kept by the marketplace until the loan is repaid or claimed
+        transfer_from_token(
+            collection_address,
+            msg_src,
+            address_marketplace,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await?;
+
+        let loan_id = self.loan_nonce;
+        self.loan_nonce += 1;
+        let loan = Loan {
+            borrower: msg_src,
+            collection_address,
+            token_id,
+            amount,
+            interest,
+            duration_ms,
+            lender: None,
+        };
+        self.loans.insert(loan_id, loan.clone());
+
+        Ok(NftMarketplaceEvent::LoanRequested { loan_id, loan })
+    }
+
+    pub async fn cancel_loan_request(
+        &mut self,
+        loan_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let loan = match self.loans.get(&loan_id) {
+            Some(loan) if loan.borrower == msg::source() && loan.lender.is_none() => loan.clone(),
+            _ => {
+                return Err(NftMarketplaceError(
+                    "This loan request does not exist or you are not the borrower".to_owned(),
+                ))
+            }
+        };
+
+        self.loans.remove(&loan_id);
+        self.return_collateral(loan_id, loan, msg::source()).await?;
+
+        Ok(NftMarketplaceEvent::LoanRequestCanceled { loan_id })
+    }
+
+    pub fn fund_loan(&mut self, loan_id: u64) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let payment = msg::value();
+        let lender = msg::source();
+        let loan = match self.loans.get_mut(&loan_id) {
+            Some(loan) if loan.lender.is_none() => loan,
+            _ => {
+                return Err(NftMarketplaceError(
+                    "This loan request does not exist or has already been funded".to_owned(),
+                ))
+            }
+        };
+        if payment != loan.amount {
+            return Err(NftMarketplaceError(format!(
+                "The attached value must be equal to the loan amount ({})",
+                loan.amount
+            )));
+        }
+
+        let repay_until = exec::block_timestamp() + loan.duration_ms;
+        loan.lender = Some((lender, repay_until));
+        // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+        msg::send_with_gas(loan.borrower, "", 0, loan.amount).expect("Error in sending value");
+
+        Ok(NftMarketplaceEvent::LoanFunded {
+            loan_id,
+            lender,
+            repay_until,
+        })
+    }
+
+    pub async fn repay_loan(
+        &mut self,
+        loan_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let payment = msg::value();
+        let (loan, lender) = match self.loans.get(&loan_id) {
+            Some(loan) if loan.borrower == msg::source() => match loan.lender {
+                Some((lender, repay_until)) if repay_until > exec::block_timestamp() => {
+                    (loan.clone(), lender)
+                }
+                Some(_) => return Err(NftMarketplaceError("The loan term has expired".to_owned())),
+                None => {
+                    return Err(NftMarketplaceError(
+                        "This loan has not been funded yet".to_owned(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(NftMarketplaceError(
+                    "This loan does not exist or you are not the borrower".to_owned(),
+                ))
+            }
+        };
+        let repayment = match loan.amount.checked_add(loan.interest) {
+            Some(repayment) if repayment == payment => repayment,
+            _ => {
+                return Err(NftMarketplaceError(
+                    "The attached value must be equal to the loan amount with interest".to_owned(),
+                ))
+            }
+        };
+
+        self.loans.remove(&loan_id);
+        self.return_collateral(loan_id, loan, msg::source()).await?;
+        msg::send_with_gas(lender, "", 0, repayment).expect("Error in sending value");
+
+        Ok(NftMarketplaceEvent::LoanRepaid { loan_id })
+    }
+
+    pub async fn claim_collateral(
+        &mut self,
+        loan_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let msg_src = msg::source();
+        let loan = match self.loans.get(&loan_id) {
+            Some(loan) => match loan.lender {
+                Some((lender, repay_until))
+                    if lender == msg_src && repay_until <= exec::block_timestamp() =>
+                {
+                    loan.clone()
+                }
+                Some((lender, _)) if lender == msg_src => {
+                    return Err(NftMarketplaceError(
+                        "The loan term has not expired yet".to_owned(),
+                    ))
+                }
+                _ => {
+                    return Err(NftMarketplaceError(
+                        "Only the lender can claim the collateral".to_owned(),
+                    ))
+                }
+            },
+            None => return Err(NftMarketplaceError("This loan does not exist".to_owned())),
+        };
+
+        self.loans.remove(&loan_id);
+        self.return_collateral(loan_id, loan, msg_src).await?;
+
+        Ok(NftMarketplaceEvent::CollateralClaimed { loan_id })
+    }
+
+    /// Transfers the collateral from the marketplace,
+    /// the loan is restored if the transfer fails
+    async fn return_collateral(
+        &mut self,
+        loan_id: u64,
+        loan: Loan,
+        to: ActorId,
+    ) -> Result<(), NftMarketplaceError> {
+        if let Err(error) = transfer_token(
+            loan.collection_address,
+            to,
+            loan.token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await
+        {
+            self.loans.insert(loan_id, loan);
+            return Err(error);
+        }
+        Ok(())
+    }
+}
@@ -17,7 +17,7 @@ pub async fn transfer_token(
     )
     .expect("Error during send message `NftAction::Transfer`")
     .await
-    .expect("Program was problem with transfer");
+    .map_err(|_| NftMarketplaceError("The collection failed to transfer the token".to_owned()))?;
 
     check_reply(reply)
 }
@@ -39,7 +39,7 @@ pub async fn transfer_from_token(
     )
     .expect("Error during `NftAction::TransferFrom`")
     .await
-    .expect("Program was problem with transfer");
+    .map_err(|_| NftMarketplaceError("The collection failed to transfer the token".to_owned()))?;
 
     check_reply(reply)
 }
@@ -50,7 +50,8 @@ pub async fn check_token_info(
     gas_for_get_token_info: u64,
     msg_src: &ActorId,
     address_marketplace: &ActorId,
-) -> Result<(ActorId, u16), NftMarketplaceError> {
+    operator_approvals: bool,
+) -> Result<Vec<(ActorId, u16)>, NftMarketplaceError> {
     let get_token_info_payload = NftAction::GetTokenInfo { token_id };
     let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
         *collection_address,
@@ -65,12 +66,14 @@ pub async fn check_token_info(
 
     let reply = check_reply(reply)?;
 
-    let (collection_owner, royalty) = if let NftEvent::TokenInfoReceived {
+    let (approval, royalty_recipients) = if let NftEvent::TokenInfoReceived {
         token_owner,
         approval,
         sellable,
         collection_owner,
         royalty,
+        royalty_recipients,
+        ..
     } = reply
     {
         // nft should be sellable
@@ -83,23 +86,273 @@ pub async fn check_token_info(
                 "Only the owner of the token can perform this action.".to_owned(),
             ));
         }
-        // must be approved by the marketplace
-        if let Some(approve_acc) = approval {
-            if approve_acc != *address_marketplace {
-                return Err(NftMarketplaceError(
-                    "No approve to the marketplace".to_owned(),
-                ));
-            }
-        } else {
+        (
+            approval,
+            royalty_recipients_or_owner(royalty_recipients, collection_owner, royalty),
+        )
+    } else {
+        return Err(NftMarketplaceError("Wrong received reply".to_owned()));
+    };
+    // must be approved by the marketplace, either for this token
+    // or as an operator of the owner if the collection supports operator approvals
+    let approved = approval == Some(*address_marketplace)
+        || (operator_approvals
+            && is_approved_for_all(
+                collection_address,
+                msg_src,
+                address_marketplace,
+                gas_for_get_token_info,
+            )
+            .await?);
+    if !approved {
+        return Err(NftMarketplaceError(
+            "No approve to the marketplace".to_owned(),
+        ));
+    }
+    Ok(royalty_recipients)
+}
+
+/// Checks whether the seller still owns the token and approves it to the marketplace.
+/// Other errors of the collection are returned as errors, so that they are not taken for a revoked listing
+pub async fn is_owned_and_approved(
+    collection_address: &ActorId,
+    token_id: u64,
+    gas_for_get_token_info: u64,
+    owner: &ActorId,
+    address_marketplace: &ActorId,
+    operator_approvals: bool,
+) -> Result<bool, NftMarketplaceError> {
+    let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+        *collection_address,
+        NftAction::GetTokenInfo { token_id },
+        gas_for_get_token_info,
+        0,
+        0,
+    )
+    .expect("Error during `NftAction::GetTokenInfo`")
+    .await
+    .map_err(|_| {
+        NftMarketplaceError("The collection failed to return the token info".to_owned())
+    })?;
+
+    let (token_owner, approval) = if let NftEvent::TokenInfoReceived {
+        token_owner,
+        approval,
+        ..
+    } = check_reply(reply)?
+    {
+        (token_owner, approval)
+    } else {
+        return Err(NftMarketplaceError("Wrong received reply".to_owned()));
+    };
+    if token_owner != *owner {
+        return Ok(false);
+    }
+    if approval == Some(*address_marketplace) {
+        return Ok(true);
+    }
+    if operator_approvals {
+        return is_approved_for_all(
+            collection_address,
+            owner,
+            address_marketplace,
+            gas_for_get_token_info,
+        )
+        .await;
+    }
+    Ok(false)
+}
+
+pub async fn is_approved_for_all(
+    collection_address: &ActorId,
+    owner: &ActorId,
+    operator: &ActorId,
+    gas_for_get_token_info: u64,
+) -> Result<bool, NftMarketplaceError> {
+    let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+        *collection_address,
+        NftAction::IsApprovedForAll {
+            owner: *owner,
+            operator: *operator,
+        },
+        gas_for_get_token_info,
+        0,
+        0,
+    )
+    .expect("Error during `NftAction::IsApprovedForAll`")
+    .await
+    .expect("Problem with is approved for all");
+
+    if let NftEvent::IsApprovedForAll(approved) = check_reply(reply)? {
+        Ok(approved)
+    } else {
+        Err(NftMarketplaceError("Wrong received reply".to_owned()))
+    }
+}
+
+/// The handshake with the collection, returns its features and owner,
+/// an error reply means that the collection does not understand the protocol of the marketplace
+pub async fn get_capabilities(
+    collection_address: &ActorId,
+    gas_for_get_token_info: u64,
+) -> Result<(Capabilities, ActorId), NftMarketplaceError> {
+    let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+        *collection_address,
+        NftAction::GetCapabilities,
+        gas_for_get_token_info,
+        0,
+        0,
+    )
+    .expect("Error during `NftAction::GetCapabilities`")
+    .await
+    .map_err(|_| {
+        NftMarketplaceError("The collection does not support the marketplace protocol".to_owned())
+    })?;
+
+    if let NftEvent::CapabilitiesReceived {
+        capabilities,
+        collection_owner,
+    } = check_reply(reply)?
+    {
+        Ok((capabilities, collection_owner))
+    } else {
+        Err(NftMarketplaceError("Wrong received reply".to_owned()))
+    }
+}
+
+/// The collection owner named by the collection in the information about the token
+pub async fn get_collection_owner(
+    collection_address: &ActorId,
+    token_id: u64,
+    gas_for_get_token_info: u64,
+) -> Result<ActorId, NftMarketplaceError> {
+    let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+        *collection_address,
+        NftAction::GetTokenInfo { token_id },
+        gas_for_get_token_info,
+        0,
+        0,
+    )
+    .expect("Error during `NftAction::GetTokenInfo`")
+    .await
+    .map_err(|_| {
+        NftMarketplaceError("The collection does not support the marketplace protocol".to_owned())
+    })?;
+
+    if let NftEvent::TokenInfoReceived {
+        collection_owner, ..
+    } = check_reply(reply)?
+    {
+        Ok(collection_owner)
+    } else {
+        Err(NftMarketplaceError("Wrong received reply".to_owned()))
+    }
+}
+
+/// Checks the token that has already been transferred to the marketplace
+pub async fn check_escrowed_token(
+    collection_address: &ActorId,
+    token_id: u64,
+    gas_for_get_token_info: u64,
+    address_marketplace: &ActorId,
+) -> Result<Vec<(ActorId, u16)>, NftMarketplaceError> {
+    let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+        *collection_address,
+        NftAction::GetTokenInfo { token_id },
+        gas_for_get_token_info,
+        0,
+        0,
+    )
+    .expect("Error during `NftAction::GetTokenInfo`")
+    .await
+    .expect("Problem with get token info");
+
+    if let NftEvent::TokenInfoReceived {
+        token_owner,
+        sellable,
+        collection_owner,
+        royalty,
+        royalty_recipients,
+        ..
+    } = check_reply(reply)?
+    {
+        if !sellable {
+            return Err(NftMarketplaceError("Nft is not sellable".to_owned()));
+        }
+        if token_owner != *address_marketplace {
             return Err(NftMarketplaceError(
-                "No approve to the marketplace".to_owned(),
+                "The token must be transferred to the marketplace".to_owned(),
             ));
         }
-        (collection_owner, royalty)
+        Ok(royalty_recipients_or_owner(
+            royalty_recipients,
+            collection_owner,
+            royalty,
+        ))
     } else {
-        return Err(NftMarketplaceError("Wrong received reply".to_owned()));
-    };
-    Ok((collection_owner, royalty))
+        Err(NftMarketplaceError("Wrong received reply".to_owned()))
+    }
+}
+
+/// A collection without royalty recipients pays the whole royalty to its owner
+fn royalty_recipients_or_owner(
+    royalty_recipients: Vec<(ActorId, u16)>,
+    collection_owner: ActorId,
+    royalty: u16,
+) -> Vec<(ActorId, u16)> {
+    if royalty_recipients.is_empty() {
+        vec![(collection_owner, royalty)]
+    } else {
+        royalty_recipients
+    }
+}
+
+pub async fn get_token_combination(
+    collection_address: &ActorId,
+    token_id: u64,
+    gas_for_get_token_info: u64,
+) -> Result<Vec<u8>, NftMarketplaceError> {
+    let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+        *collection_address,
+        NftAction::GetTokenCombination { token_id },
+        gas_for_get_token_info,
+        0,
+        0,
+    )
+    .expect("Error during `NftAction::GetTokenCombination`")
+    .await
+    .expect("Problem with get token combination");
+
+    if let NftEvent::TokenCombinationReceived { combination } = check_reply(reply)? {
+        Ok(combination)
+    } else {
+        Err(NftMarketplaceError("Wrong received reply".to_owned()))
+    }
+}
+
+pub async fn set_user(
+    collection_address: ActorId,
+    token_id: u64,
+    user: ActorId,
+    expires: u64,
+    gas_for_transfer: u64,
+) -> Result<NftEvent, NftMarketplaceError> {
+    let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+        collection_address,
+        NftAction::SetUser {
+            token_id,
+            user,
+            expires,
+        },
+        gas_for_transfer,
+        0,
+        0,
+    )
+    .expect("Error during `NftAction::SetUser`")
+    .await
+    .expect("Program was problem with set user");
+
+    check_reply(reply)
 }
 
 fn check_reply(reply: Result<NftEvent, NftError>) -> Result<NftEvent, NftMarketplaceError> {
@@ -1,6 +1,6 @@
 use crate::nft_messages::*;
 use crate::payment::*;
-use crate::NftMarketplace;
+use crate::{NftMarketplace, Price};
 use gstd::{exec, msg, prelude::*, ActorId};
 use nft_marketplace_io::*;
 
@@ -9,6 +9,9 @@ impl NftMarketplace {
         &mut self,
         collection_address: ActorId,
         token_id: u64,
+        expires_at: Option<u64>,
+        recipient: Option<ActorId>,
+        referrer: Option<ActorId>,
     ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
         let current_price = msg::value();
         if current_price < self.config.minimum_transfer_value {
@@ -22,6 +25,14 @@ impl NftMarketplace {
                 "This collection address is not in the marketplace".to_owned(),
             ));
         }
+        let current_time = exec::block_timestamp();
+        if let Some(expires_at) = expires_at {
+            if expires_at <= current_time {
+                return Err(NftMarketplaceError(
+                    "The expiration time of the offer must be in the future".to_owned(),
+                ));
+            }
+        }
 
         let get_token_info_payload = NftAction::GetTokenInfo { token_id };
         let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
@@ -35,35 +46,69 @@ impl NftMarketplace {
         .await
         .expect("Problem with get token info");
 
-        match reply {
+        let token_owner = match reply {
             Err(NftError(error_string)) => Err(NftMarketplaceError(error_string.clone())),
-            Ok(NftEvent::TokenInfoReceived { sellable, .. }) => {
+            Ok(NftEvent::TokenInfoReceived {
+                sellable,
+                token_owner,
+                ..
+            }) => {
                 if !sellable {
                     Err(NftMarketplaceError("Nft is not sellable".to_owned()))
                 } else {
-                    Ok(())
+                    Ok(token_owner)
                 }
             }
             _ => Err(NftMarketplaceError("Wrong received reply".to_owned())),
         }?;
 
+        let creator = msg::source();
+        // the listed token is kept by the marketplace, then the seller is the owner of the listing
+        let seller = self
+            .sales
+            .get(&(collection_address, token_id))
+            .map_or(token_owner, |sale| sale.token_owner);
+        check_referrer(referrer, &creator, recipient, &seller)?;
         let offer = Offer {
             collection_address,
             token_id,
-            creator: msg::source(),
+            creator,
         };
-        self.offers
-            .entry(offer.clone())
-            .and_modify(|price| {
-                msg::send_with_gas(offer.creator, "", 0, *price).expect("Error in sending value");
-                *price = current_price;
-            })
-            .or_insert(current_price);
+        // the new offer replaces the previous offer of the same creator for this token
+        if let Some(previous_offer) = self.offers.insert(
+            offer.clone(),
+            OfferInfo {
+                price: current_price,
+                expires_at,
+                recipient,
+                referrer,
+            },
+        ) {
+            self.counter_offers.remove(&offer);
+            msg::send_with_gas(creator, "", 0, previous_offer.price)
+                .expect("Error in sending value");
+        }
+
+        // the delayed message returns the value to the creator if the offer is not accepted in time
+        if let Some(expires_at) = expires_at {
+            let delay = (expires_at - current_time) / self.config.ms_in_block as u64 + 1;
+            msg::send_with_gas_delayed(
+                exec::program_id(),
+                NftMarketplaceAction::CleanupExpiredOffers {
+                    offers: vec![offer],
+                },
+                self.config.gas_for_cleanup_offers,
+                0,
+                delay.try_into().unwrap_or(u32::MAX),
+            )
+            .expect("Error in sending delayed message");
+        }
 
         Ok(NftMarketplaceEvent::OfferCreated {
             collection_address,
             token_id,
             price: current_price,
+            expires_at,
         })
     }
 
@@ -78,15 +123,16 @@ impl NftMarketplace {
             creator: msg::source(),
         };
 
-        if let Some((offer, price)) = self.offers.get_key_value(&offer) {
-            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
-            msg::send_with_gas(offer.creator, "", 0, *price).expect("Error in sending value");
+        let price = if let Some(offer_info) = self.offers.remove(&offer) {
+            offer_info.price
         } else {
             return Err(NftMarketplaceError(
                 "This offer does not exist or you are not the creator of the offer".to_owned(),
             ));
-        }
-        self.offers.remove(&offer);
+        };
+        self.counter_offers.remove(&offer);
+        // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+        msg::send_with_gas(offer.creator, "", 0, price).expect("Error in sending value");
 
         Ok(NftMarketplaceEvent::OfferCanceled {
             collection_address,
@@ -97,10 +143,362 @@ impl NftMarketplace {
     pub async fn accept_offer(
         &mut self,
         offer: Offer,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let price = if let Some(offer_info) = self.offers.get(&offer) {
+            if offer_info
+                .expires_at
+                .map_or(false, |expires_at| expires_at <= exec::block_timestamp())
+            {
+                return Err(NftMarketplaceError("This offer has expired".to_owned()));
+            }
+            offer_info.price
+        } else {
+            return Err(NftMarketplaceError("This offer does not exist".to_owned()));
+        };
+
+        let referral = self.settle_offer(&offer, msg::source(), price).await?;
+
+        Ok(NftMarketplaceEvent::OfferAccepted { offer, referral })
+    }
+
+    pub async fn counter_offer(
+        &mut self,
+        offer: Offer,
+        price: u128,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let offer_price = if let Some(offer_info) = self.offers.get(&offer) {
+            offer_info.price
+        } else {
+            return Err(NftMarketplaceError("This offer does not exist".to_owned()));
+        };
+        if price <= offer_price {
+            return Err(NftMarketplaceError(
+                "The counter-offer price must be greater than the offer price".to_owned(),
+            ));
+        }
+
+        // only the current token owner can make a counter-offer
+        let address_marketplace = exec::program_id();
+        let msg_src = msg::source();
+        check_token_info(
+            &offer.collection_address,
+            offer.token_id,
+            self.config.gas_for_get_token_info,
+            &msg_src,
+            &address_marketplace,
+            self.capabilities(&offer.collection_address)
+                .operator_approvals,
+        )
+        .await?;
+
+        if !self.offers.contains_key(&offer) {
+            return Err(NftMarketplaceError("This offer does not exist".to_owned()));
+        }
+        self.counter_offers.insert(offer.clone(), (msg_src, price));
+
+        Ok(NftMarketplaceEvent::CounterOfferCreated { offer, price })
+    }
+
+    pub async fn accept_counter_offer(
+        &mut self,
+        offer: Offer,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let top_up = msg::value();
+        let msg_src = msg::source();
+        if offer.creator != msg_src {
+            return Err(NftMarketplaceError(
+                "Only the creator of the offer can accept the counter-offer".to_owned(),
+            ));
+        }
+        let (offer_info, (token_owner, price)) =
+            match (self.offers.get(&offer), self.counter_offers.get(&offer)) {
+                (Some(offer_info), Some(counter_offer)) => (offer_info, *counter_offer),
+                _ => {
+                    return Err(NftMarketplaceError(
+                        "This counter-offer does not exist".to_owned(),
+                    ))
+                }
+            };
+        if offer_info
+            .expires_at
+            .map_or(false, |expires_at| expires_at <= exec::block_timestamp())
+        {
+            return Err(NftMarketplaceError("This offer has expired".to_owned()));
+        }
+        let offer_price = offer_info.price;
+        if offer_price.checked_add(top_up) != Some(price) {
+            return Err(NftMarketplaceError(format!(
+                "The attached value must be equal to the difference between the prices ({})",
+                price - offer_price
+            )));
+        }
+
+        // the top-up is returned by `refund_on_error` if the offer can't be settled
+        let referral = self.settle_offer(&offer, token_owner, price).await?;
+
+        Ok(NftMarketplaceEvent::CounterOfferAccepted {
+            offer,
+            price,
+            referral,
+        })
+    }
+
+    /// Transfers the token from its owner to the creator of the offer and pays the price,
+    /// returns the referral payout
+    async fn settle_offer(
+        &mut self,
+        offer: &Offer,
+        token_owner: ActorId,
+        price: Price,
+    ) -> Result<Option<(ActorId, u128)>, NftMarketplaceError> {
+        let key = (offer.collection_address, offer.token_id);
+        if let Some(sale) = self.sales.get(&key) {
+            if sale.token_owner != token_owner {
+                return Err(NftMarketplaceError(
+                    "Only the seller can accept the offer for the token on sale".to_owned(),
+                ));
+            }
+        }
+        if self.auctions.contains_key(&key) {
+            return Err(NftMarketplaceError(
+                "This token is on auction, cancel the auction if you wish to accept the offer"
+                    .to_owned(),
+            ));
+        }
+
+        // the offer (and the sale, if the token is listed) is taken out before the asynchronous calls,
+        // so that it can't be canceled or accepted twice in the meantime
+        let escrow = self.offers.remove(offer).expect("Can't be None");
+        let counter_offer = self.counter_offers.remove(offer);
+        let sale = self.remove_sale(&key);
+
+        let result = if let Some(sale) = sale.as_ref() {
+            self.transfer_listed_token(offer, &escrow, sale, price)
+                .await
+        } else {
+            self.transfer_offered_token(offer, &escrow, token_owner, price)
+                .await
+        };
+        if result.is_err() {
+            if let Some(sale) = sale {
+                self.restore_sale(key, sale).await;
+            }
+            if self.offers.contains_key(offer) {
+                // the creator has placed a new offer on this token in the meantime
+                msg::send_with_gas(offer.creator, "", 0, escrow.price)
+                    .expect("Error in sending value");
+            } else {
+                self.offers.insert(offer.clone(), escrow);
+                if let Some(counter_offer) = counter_offer {
+                    self.counter_offers.insert(offer.clone(), counter_offer);
+                }
+            }
+        }
+        result
+    }
+
+    /// The token is already in the marketplace escrow, so it is transferred directly
+    async fn transfer_listed_token(
+        &self,
+        offer: &Offer,
+        offer_info: &OfferInfo,
+        sale: &NftInfoForSale,
+        price: Price,
+    ) -> Result<Option<(ActorId, u128)>, NftMarketplaceError> {
+        self.transfer_sold_token(
+            offer.collection_address,
+            offer.token_id,
+            sale,
+            offer_info.recipient.unwrap_or(offer.creator),
+        )
+        .await?;
+
+        // transfer the referral fee to the referrer, the rest of the value to token owner
+        // and percent to collection creator
+        // the token could have been transferred to the referrer after the offer was made
+        let referral = referral_transfer(
+            offer_info
+                .referrer
+                .filter(|referrer| *referrer != sale.token_owner),
+            price,
+            sale.referral_rate,
+            self.config.minimum_transfer_value,
+        );
+        currency_transfer_after_referral(
+            &sale.royalty_recipients,
+            sale.token_owner,
+            price,
+            referral,
+            self.config.minimum_transfer_value,
+            self.gas_for_token_owner(&sale.token_owner),
+        );
+        Ok(referral)
+    }
+
+    async fn transfer_offered_token(
+        &self,
+        offer: &Offer,
+        offer_info: &OfferInfo,
+        token_owner: ActorId,
+        price: Price,
+    ) -> Result<Option<(ActorId, u128)>, NftMarketplaceError> {
+        // check token info
+        let address_marketplace = exec::program_id();
+        let royalty_recipients = check_token_info(
+            &offer.collection_address,
+            offer.token_id,
+            self.config.gas_for_get_token_info,
+            &token_owner,
+            &address_marketplace,
+            self.capabilities(&offer.collection_address)
+                .operator_approvals,
+        )
+        .await?;
+
+        transfer_from_token(
+            offer.collection_address,
+            token_owner,
+            offer_info.recipient.unwrap_or(offer.creator),
+            offer.token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await?;
+
+        // the token is not listed, so the owner hasn't agreed to pay a referral fee
+        currency_transfer(
+            &royalty_recipients,
+            token_owner,
+            price,
+            self.config.minimum_transfer_value,
+            0,
+        );
+        Ok(None)
+    }
+
+    /// Returns the value of the given offers that have expired,
+    /// the offers that don't exist or haven't expired yet are skipped
+    pub fn cleanup_expired_offers(
+        &mut self,
+        offers: Vec<Offer>,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let current_time = exec::block_timestamp();
+        let mut expired_offers = Vec::new();
+        for offer in offers {
+            // the offer could have been replaced by a new offer with another expiration time
+            let expired = self.offers.get(&offer).map_or(false, |offer_info| {
+                offer_info
+                    .expires_at
+                    .map_or(false, |expires_at| expires_at <= current_time)
+            });
+            if !expired {
+                continue;
+            }
+            let price = self.offers.remove(&offer).expect("Can't be None").price;
+            self.counter_offers.remove(&offer);
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(offer.creator, "", 0, price).expect("Error in sending value");
+            expired_offers.push(offer);
+        }
+
+        Ok(NftMarketplaceEvent::ExpiredOffersCleanedUp {
+            offers: expired_offers,
+        })
+    }
+
+    pub async fn create_collection_offer(
+        &mut self,
+        collection_address: ActorId,
+        quantity: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let value = msg::value();
+        if !self.collection_to_owner.contains_key(&collection_address) {
+            return Err(NftMarketplaceError(
+                "This collection address is not in the marketplace".to_owned(),
+            ));
+        }
+        if quantity == 0 {
+            return Err(NftMarketplaceError(
+                "The quantity must be greater than zero".to_owned(),
+            ));
+        }
+        if value % quantity as u128 != 0 {
+            return Err(NftMarketplaceError(
+                "The value must be a multiple of the quantity".to_owned(),
+            ));
+        }
+        let price = value / quantity as u128;
+        if price < self.config.minimum_transfer_value {
+            return Err(NftMarketplaceError(format!(
+                "The price must be greater than existential deposit ({})",
+                self.config.minimum_transfer_value
+            )));
+        }
+
+        let offer = CollectionOffer {
+            collection_address,
+            creator: msg::source(),
+        };
+        // the new offer replaces the previous collection offer of the same creator
+        if let Some(previous_info) = self.remove_collection_offer(&offer) {
+            msg::send_with_gas(
+                offer.creator,
+                "",
+                0,
+                previous_info.price * previous_info.quantity as u128,
+            )
+            .expect("Error in sending value");
+        }
+        self.insert_collection_offer(offer.clone(), CollectionOfferInfo { price, quantity });
+
+        // the new bid is filled at once with the tokens on sale that cross its price
+        let trades = self.match_bid(&offer).await;
+        if !trades.is_empty() {
+            // the unfilled part of the offer stays in the book
+            return Ok(NftMarketplaceEvent::OrdersMatched {
+                collection_address,
+                trades,
+                remaining_offer: self.collection_offers.get(&offer).cloned(),
+            });
+        }
+
+        Ok(NftMarketplaceEvent::CollectionOfferCreated {
+            collection_address,
+            price,
+            quantity,
+        })
+    }
+
+    pub fn cancel_collection_offer(
+        &mut self,
+        collection_address: ActorId,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let offer = CollectionOffer {
+            collection_address,
+            creator: msg::source(),
+        };
+        let info = if let Some(info) = self.remove_collection_offer(&offer) {
+            info
+        } else {
+            return Err(NftMarketplaceError(
+                "This offer does not exist or you are not the creator of the offer".to_owned(),
+            ));
+        };
+
+        // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+        msg::send_with_gas(offer.creator, "", 0, info.price * info.quantity as u128)
+            .expect("Error in sending value");
+
+        Ok(NftMarketplaceEvent::CollectionOfferCanceled { collection_address })
+    }
+
+    pub async fn accept_collection_offer(
+        &mut self,
+        offer: CollectionOffer,
+        token_id: u64,
     ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
         if self
             .sales
-            .contains_key(&(offer.collection_address, offer.token_id))
+            .contains_key(&(offer.collection_address, token_id))
         {
             return Err(NftMarketplaceError(
                 "This token is on sale, cancel the sale if you wish to accept the offer".to_owned(),
@@ -108,26 +506,70 @@ impl NftMarketplace {
         }
         if self
             .auctions
-            .contains_key(&(offer.collection_address, offer.token_id))
+            .contains_key(&(offer.collection_address, token_id))
         {
             return Err(NftMarketplaceError(
                 "This token is on auction, cancel the auction if you wish to accept the offer"
                     .to_owned(),
             ));
         }
-        if !self.offers.contains_key(&offer) {
-            return Err(NftMarketplaceError("This offer does not exist".to_owned()));
+        // one token of the offer is reserved before the asynchronous calls,
+        // so that the same unit can't be filled twice
+        let price = match self.collection_offers.get_mut(&offer) {
+            Some(info) if info.quantity > 0 => {
+                info.quantity -= 1;
+                info.price
+            }
+            _ => return Err(NftMarketplaceError("This offer does not exist".to_owned())),
+        };
+
+        let result = self.fill_collection_offer(&offer, token_id, price).await;
+        self.settle_reserved_unit(&offer, price, result.is_ok());
+        result?;
+
+        Ok(NftMarketplaceEvent::CollectionOfferAccepted {
+            offer,
+            token_id,
+            price,
+        })
+    }
+
+    /// Settles the unit of the collection offer that was reserved before the transfer.
+    /// The offer may have been canceled or replaced during the transfer,
+    /// so an unfilled unit only goes back to an offer with the same price, otherwise it is refunded
+    pub fn settle_reserved_unit(&mut self, offer: &CollectionOffer, price: Price, filled: bool) {
+        match self.collection_offers.get_mut(offer) {
+            Some(info) if filled => {
+                if info.quantity == 0 {
+                    self.remove_collection_offer(offer);
+                }
+            }
+            Some(info) if info.price == price => info.quantity += 1,
+            _ if filled => {}
+            _ => {
+                // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+                msg::send_with_gas(offer.creator, "", 0, price).expect("Error in sending value");
+            }
         }
+    }
 
+    async fn fill_collection_offer(
+        &self,
+        offer: &CollectionOffer,
+        token_id: u64,
+        price: u128,
+    ) -> Result<(), NftMarketplaceError> {
         // check token info
         let address_marketplace = exec::program_id();
         let msg_src = msg::source();
-        let (collection_owner, royalty) = check_token_info(
+        let royalty_recipients = check_token_info(
             &offer.collection_address,
-            offer.token_id,
+            token_id,
             self.config.gas_for_get_token_info,
             &msg_src,
             &address_marketplace,
+            self.capabilities(&offer.collection_address)
+                .operator_approvals,
         )
         .await?;
 
@@ -135,24 +577,193 @@ impl NftMarketplace {
             offer.collection_address,
             msg_src,
             offer.creator,
-            offer.token_id,
+            token_id,
             self.config.gas_for_transfer_token,
         )
         .await?;
 
-        let price = self.offers.get(&offer).expect("Can't be None");
-
         // transfer value to token owner and percent to collection creator
         currency_transfer(
-            collection_owner,
+            &royalty_recipients,
             msg_src,
-            *price,
-            royalty,
+            price,
             self.config.minimum_transfer_value,
+            0,
         );
+        Ok(())
+    }
+
+    pub fn create_trait_offer(
+        &mut self,
+        collection_address: ActorId,
+        layer: u32,
+        option: u8,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let price = msg::value();
+        if price < self.config.minimum_transfer_value {
+            return Err(NftMarketplaceError(format!(
+                "The price must be greater than existential deposit ({})",
+                self.config.minimum_transfer_value
+            )));
+        }
+        if !self.collection_to_owner.contains_key(&collection_address) {
+            return Err(NftMarketplaceError(
+                "This collection address is not in the marketplace".to_owned(),
+            ));
+        }
+        if !self.capabilities(&collection_address).token_combinations {
+            return Err(NftMarketplaceError(
+                "This collection does not support trait offers".to_owned(),
+            ));
+        }
+
+        let offer = TraitOffer {
+            collection_address,
+            layer,
+            option,
+            creator: msg::source(),
+        };
+        // the new offer replaces the previous offer of the same creator for this trait
+        if let Some(previous_price) = self.trait_offers.insert(offer.clone(), price) {
+            msg::send_with_gas(offer.creator, "", 0, previous_price)
+                .expect("Error in sending value");
+        }
+
+        Ok(NftMarketplaceEvent::TraitOfferCreated {
+            collection_address,
+            layer,
+            option,
+            price,
+        })
+    }
+
+    pub fn cancel_trait_offer(
+        &mut self,
+        collection_address: ActorId,
+        layer: u32,
+        option: u8,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let offer = TraitOffer {
+            collection_address,
+            layer,
+            option,
+            creator: msg::source(),
+        };
+        let price = if let Some(price) = self.trait_offers.remove(&offer) {
+            price
+        } else {
+            return Err(NftMarketplaceError(
+                "This offer does not exist or you are not the creator of the offer".to_owned(),
+            ));
+        };
+
+        // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+        msg::send_with_gas(offer.creator, "", 0, price).expect("Error in sending value");
+
+        Ok(NftMarketplaceEvent::TraitOfferCanceled {
+            collection_address,
+            layer,
+            option,
+        })
+    }
+
+    pub async fn accept_trait_offer(
+        &mut self,
+        offer: TraitOffer,
+        token_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        if self
+            .sales
+            .contains_key(&(offer.collection_address, token_id))
+        {
+            return Err(NftMarketplaceError(
+                "This token is on sale, cancel the sale if you wish to accept the offer".to_owned(),
+            ));
+        }
+        if self
+            .auctions
+            .contains_key(&(offer.collection_address, token_id))
+        {
+            return Err(NftMarketplaceError(
+                "This token is on auction, cancel the auction if you wish to accept the offer"
+                    .to_owned(),
+            ));
+        }
+        // the offer is taken out before the asynchronous calls,
+        // so that it can't be accepted twice
+        let price = if let Some(price) = self.trait_offers.remove(&offer) {
+            price
+        } else {
+            return Err(NftMarketplaceError("This offer does not exist".to_owned()));
+        };
 
-        self.offers.remove(&offer);
+        let result = self.fill_trait_offer(&offer, token_id, price).await;
+        if result.is_err() {
+            if self.trait_offers.contains_key(&offer) {
+                // the creator has placed a new offer on the same trait in the meantime
+                msg::send_with_gas(offer.creator, "", 0, price).expect("Error in sending value");
+            } else {
+                self.trait_offers.insert(offer.clone(), price);
+            }
+        }
+        result?;
 
-        Ok(NftMarketplaceEvent::OfferAccepted { offer })
+        Ok(NftMarketplaceEvent::TraitOfferAccepted {
+            offer,
+            token_id,
+            price,
+        })
+    }
+
+    async fn fill_trait_offer(
+        &self,
+        offer: &TraitOffer,
+        token_id: u64,
+        price: u128,
+    ) -> Result<(), NftMarketplaceError> {
+        let combination = get_token_combination(
+            &offer.collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+        )
+        .await?;
+        if combination.get(offer.layer as usize) != Some(&offer.option) {
+            return Err(NftMarketplaceError(
+                "The token does not match the offer".to_owned(),
+            ));
+        }
+
+        // check token info
+        let address_marketplace = exec::program_id();
+        let msg_src = msg::source();
+        let royalty_recipients = check_token_info(
+            &offer.collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            &msg_src,
+            &address_marketplace,
+            self.capabilities(&offer.collection_address)
+                .operator_approvals,
+        )
+        .await?;
+
+        transfer_from_token(
+            offer.collection_address,
+            msg_src,
+            offer.creator,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await?;
+
+        // transfer value to token owner and percent to collection creator
+        currency_transfer(
+            &royalty_recipients,
+            msg_src,
+            price,
+            self.config.minimum_transfer_value,
+            0,
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,249 @@
+use crate::payment::*;
+use crate::{CollectionId, NftMarketplace, Price, TokenId};
+use core::{cmp::Reverse, ops::Bound};
+use gstd::{msg, prelude::*, ActorId};
+use nft_marketplace_io::*;
+
+/// The asks of a collection are its tokens on sale,
+/// the bids are the collection offers.
+/// A new order that crosses the best order on the other side is executed at once
+/// at the price of the order that was already in the book.
+impl NftMarketplace {
+    /// Adds the sale together with its ask to the price index of the collection
+    pub fn insert_sale(&mut self, key: (CollectionId, TokenId), nft: NftInfoForSale) {
+        let price = nft.price;
+        if let Some(previous) = self.sales.insert(key, nft) {
+            self.remove_ask(key, previous.price);
+        }
+        self.ask_index
+            .entry(key.0)
+            .or_default()
+            .insert((price, key.1));
+    }
+
+    /// Removes the sale together with its ask from the price index of the collection
+    pub fn remove_sale(&mut self, key: &(CollectionId, TokenId)) -> Option<NftInfoForSale> {
+        let nft = self.sales.remove(key)?;
+        self.remove_ask(*key, nft.price);
+        Some(nft)
+    }
+
+    fn remove_ask(
+        &mut self,
+        (collection_address, token_id): (CollectionId, TokenId),
+        price: Price,
+    ) {
+        if let Some(asks) = self.ask_index.get_mut(&collection_address) {
+            asks.remove(&(price, token_id));
+            if asks.is_empty() {
+                self.ask_index.remove(&collection_address);
+            }
+        }
+    }
+
+    /// Adds the collection offer together with its bid to the price index of the collection
+    pub fn insert_collection_offer(&mut self, offer: CollectionOffer, info: CollectionOfferInfo) {
+        let price = info.price;
+        if let Some(previous) = self.collection_offers.insert(offer.clone(), info) {
+            self.remove_bid(&offer, previous.price);
+        }
+        self.bid_index
+            .entry(offer.collection_address)
+            .or_default()
+            .insert((Reverse(price), offer.creator));
+    }
+
+    /// Removes the collection offer together with its bid from the price index of the collection
+    pub fn remove_collection_offer(
+        &mut self,
+        offer: &CollectionOffer,
+    ) -> Option<CollectionOfferInfo> {
+        let info = self.collection_offers.remove(offer)?;
+        self.remove_bid(offer, info.price);
+        Some(info)
+    }
+
+    fn remove_bid(&mut self, offer: &CollectionOffer, price: Price) {
+        if let Some(bids) = self.bid_index.get_mut(&offer.collection_address) {
+            bids.remove(&(Reverse(price), offer.creator));
+            if bids.is_empty() {
+                self.bid_index.remove(&offer.collection_address);
+            }
+        }
+    }
+
+    /// Asks (price, token id, seller) from the lowest price, after the given ask if there is one
+    pub fn asks(
+        &self,
+        collection_address: &ActorId,
+        after: Option<(Price, TokenId)>,
+    ) -> impl Iterator<Item = (Price, TokenId, ActorId)> + '_ {
+        let collection_address = *collection_address;
+        let start = after.map_or(Bound::Unbounded, Bound::Excluded);
+        self.ask_index
+            .get(&collection_address)
+            .into_iter()
+            .flat_map(move |asks| asks.range((start, Bound::Unbounded)))
+            .filter_map(move |(price, token_id)| {
+                self.sales
+                    .get(&(collection_address, *token_id))
+                    .map(|nft| (*price, *token_id, nft.token_owner))
+            })
+    }
+
+    /// Bids (price, creator, quantity) from the highest price
+    pub fn bids(
+        &self,
+        collection_address: &ActorId,
+    ) -> impl Iterator<Item = (Price, ActorId, u64)> + '_ {
+        let collection_address = *collection_address;
+        self.bid_index
+            .get(&collection_address)
+            .into_iter()
+            .flatten()
+            .filter_map(move |(Reverse(price), creator)| {
+                let offer = CollectionOffer {
+                    collection_address,
+                    creator: *creator,
+                };
+                self.collection_offers
+                    .get(&offer)
+                    .filter(|info| info.quantity > 0)
+                    .map(|info| (*price, *creator, info.quantity))
+            })
+    }
+
+    /// Fills the new collection offer with the tokens on sale
+    /// whose price does not exceed the price of the offer
+    pub async fn match_bid(&mut self, offer: &CollectionOffer) -> Vec<Trade> {
+        let mut trades = Vec::new();
+        // the search continues after the last ask, so that an ask that failed to be executed is skipped
+        let mut last_ask = None;
+        while let Some(info) = self.collection_offers.get(offer) {
+            if info.quantity == 0 {
+                break;
+            }
+            let bid_price = info.price;
+            let best_ask = self
+                .asks(&offer.collection_address, last_ask)
+                .find(|(_, _, seller)| *seller != offer.creator);
+            let (price, token_id, seller) = match best_ask {
+                Some(ask) if ask.0 <= bid_price => ask,
+                _ => break,
+            };
+            last_ask = Some((price, token_id));
+            if self.execute_trade(offer, token_id, price).await.is_ok() {
+                trades.push(Trade {
+                    token_id,
+                    seller,
+                    buyer: offer.creator,
+                    price,
+                });
+            }
+        }
+        trades
+    }
+
+    /// Sells the newly listed token to the best collection offer if it crosses the price
+    pub async fn match_ask(&mut self, collection_address: ActorId, token_id: u64) -> Option<Trade> {
+        let (ask_price, seller) = self
+            .sales
+            .get(&(collection_address, token_id))
+            .map(|nft| (nft.price, nft.token_owner))?;
+        let (price, buyer, _) = self
+            .bids(&collection_address)
+            .find(|(_, creator, _)| *creator != seller)?;
+        if price < ask_price {
+            return None;
+        }
+
+        let offer = CollectionOffer {
+            collection_address,
+            creator: buyer,
+        };
+        self.execute_trade(&offer, token_id, price).await.ok()?;
+        Some(Trade {
+            token_id,
+            seller,
+            buyer,
+            price,
+        })
+    }
+
+    /// Transfers the token on sale to the creator of the collection offer,
+    /// the sale and one unit of the offer are reserved before the transfer
+    async fn execute_trade(
+        &mut self,
+        offer: &CollectionOffer,
+        token_id: u64,
+        price: Price,
+    ) -> Result<(), NftMarketplaceError> {
+        let key = (offer.collection_address, token_id);
+        let bid_price = match self.collection_offers.get_mut(offer) {
+            Some(info) if info.quantity > 0 => {
+                info.quantity -= 1;
+                info.price
+            }
+            _ => return Err(NftMarketplaceError("This offer does not exist".to_owned())),
+        };
+        let sale = self.remove_sale(&key).expect("Can't be None");
+
+        let result = self
+            .transfer_sold_token(offer.collection_address, token_id, &sale, offer.creator)
+            .await;
+        self.settle_reserved_unit(offer, bid_price, result.is_ok());
+        if let Err(error) = result {
+            self.restore_sale(key, sale).await;
+            return Err(error);
+        }
+
+        // transfer value to token owner and percent to collection creator
+        currency_transfer(
+            &sale.royalty_recipients,
+            sale.token_owner,
+            price,
+            self.config.minimum_transfer_value,
+            self.gas_for_token_owner(&sale.token_owner),
+        );
+        // the difference between the price of the offer and the price of the trade is returned
+        if bid_price > price {
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(offer.creator, "", 0, bid_price - price)
+                .expect("Error in sending value");
+        }
+        Ok(())
+    }
+
+    /// Price levels (price, number of tokens) of both sides of the book, from the best price
+    pub fn order_book_depth(
+        &self,
+        collection_address: &ActorId,
+        depth: u32,
+    ) -> (Vec<(Price, u64)>, Vec<(Price, u64)>) {
+        let asks = price_levels(
+            self.asks(collection_address, None)
+                .map(|(price, _, _)| (price, 1)),
+            depth,
+        );
+        let bids = price_levels(
+            self.bids(collection_address)
+                .map(|(price, _, quantity)| (price, quantity)),
+            depth,
+        );
+        (asks, bids)
+    }
+}
+
+fn price_levels(orders: impl Iterator<Item = (Price, u64)>, depth: u32) -> Vec<(Price, u64)> {
+    let mut levels: Vec<(Price, u64)> = Vec::new();
+    for (price, quantity) in orders {
+        if let Some(level) = levels.last_mut().filter(|level| level.0 == price) {
+            level.1 += quantity;
+        } else if levels.len() == depth as usize {
+            break;
+        } else {
+            levels.push((price, quantity));
+        }
+    }
+    levels
+}
@@ -1,22 +1,108 @@
-use gstd::{msg, ActorId};
+use gstd::{msg, prelude::*, ActorId};
+use nft_marketplace_io::NftMarketplaceError;
 
+/// Pays each royalty recipient its share of the price and the rest to the token owner,
+/// a share that does not exceed the minimum transfer value stays with the token owner
 pub fn currency_transfer(
-    collection_owner: ActorId,
+    royalty_recipients: &[(ActorId, u16)],
     token_owner: ActorId,
     price: u128,
-    royalty: u16,
     minimum_transfer_value: u128,
+    gas_for_token_owner: u64,
 ) {
-    // calculate the percentage to the creator of the collection
-    // current_price * royalty / 10_000
-    let percent_to_collection_creator = price * (royalty as u128) / 10_000u128;
-    if percent_to_collection_creator > minimum_transfer_value {
+    currency_transfer_after_referral(
+        royalty_recipients,
+        token_owner,
+        price,
+        None,
+        minimum_transfer_value,
+        gas_for_token_owner,
+    )
+}
+
+/// The same as `currency_transfer`, but the referral fee that has been paid out of the price
+/// is taken from the part of the token owner, the royalty is still counted on the whole price
+pub fn currency_transfer_after_referral(
+    royalty_recipients: &[(ActorId, u16)],
+    token_owner: ActorId,
+    price: u128,
+    referral: Option<(ActorId, u128)>,
+    minimum_transfer_value: u128,
+    gas_for_token_owner: u64,
+) {
+    let mut value_to_token_owner = price - referral.map_or(0, |(_, fee)| fee);
+    for (recipient, royalty) in royalty_recipients {
+        // current_price * royalty / 10_000
+        let percent_to_recipient = price * (*royalty as u128) / 10_000u128;
+        if percent_to_recipient > minimum_transfer_value
+            && percent_to_recipient <= value_to_token_owner
+        {
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(*recipient, "", 0, percent_to_recipient)
+                .expect("Error in sending value");
+            value_to_token_owner -= percent_to_recipient;
+        }
+    }
+    msg::send_with_gas(token_owner, "", gas_for_token_owner, value_to_token_owner)
+        .expect("Error in sending value");
+}
+
+/// Pays the referral fee to the referrer of the buyer,
+/// only if the seller has agreed to pay it by setting the referral rate of the listing,
+/// the returned payout is passed to `currency_transfer_after_referral`
+pub fn referral_transfer(
+    referrer: Option<ActorId>,
+    price: u128,
+    referral_rate: Option<u16>,
+    minimum_transfer_value: u128,
+) -> Option<(ActorId, u128)> {
+    let referrer = referrer?;
+    let referral_rate = referral_rate?;
+    // current_price * referral_rate / 10_000
+    let referral_fee = price * (referral_rate as u128) / 10_000u128;
+    if referral_fee > minimum_transfer_value {
         // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
-        msg::send_with_gas(collection_owner, "", 0, percent_to_collection_creator)
-            .expect("Error in sending value");
-        msg::send_with_gas(token_owner, "", 0, price - percent_to_collection_creator)
-            .expect("Error in sending value");
+        msg::send_with_gas(referrer, "", 0, referral_fee).expect("Error in sending value");
+        Some((referrer, referral_fee))
     } else {
-        msg::send_with_gas(token_owner, "", 0, price).expect("Error in sending value");
+        None
+    }
+}
+
+/// The referrer must be a third party, otherwise a party of the trade takes the fee itself
+pub fn check_referrer(
+    referrer: Option<ActorId>,
+    buyer: &ActorId,
+    recipient: Option<ActorId>,
+    seller: &ActorId,
+) -> Result<(), NftMarketplaceError> {
+    if let Some(referrer) = referrer {
+        if referrer == *buyer || Some(referrer) == recipient || referrer == *seller {
+            return Err(NftMarketplaceError(
+                "The referrer can't be the buyer, the recipient or the seller".to_owned(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn check_referral_rate(referral_rate: u16) -> Result<(), NftMarketplaceError> {
+    if referral_rate > 10_000 {
+        return Err(NftMarketplaceError(
+            "The referral rate can't be more than 10000 basis points".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the value attached to the message if the action has failed
+pub fn refund_on_error<T>(
+    result: Result<T, NftMarketplaceError>,
+) -> Result<T, NftMarketplaceError> {
+    let value = msg::value();
+    if result.is_err() && value > 0 {
+        // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+        msg::send_with_gas(msg::source(), "", 0, value).expect("Error in sending value");
     }
+    result
 }
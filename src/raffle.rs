@@ -0,0 +1,236 @@
+use crate::nft_messages::*;
+use crate::payment::*;
+use crate::NftMarketplace;
+use gstd::{collections::HashMap, exec, msg, prelude::*, ActorId};
+use nft_marketplace_io::*;
+
+/// The tickets are stored one entry per ticket, so their number is limited
+const MAX_TICKETS: u32 = 10_000;
+
+impl NftMarketplace {
+    pub async fn create_raffle(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+        ticket_price: u128,
+        max_tickets: u32,
+        min_tickets: u32,
+        ends_at: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        if !self.collection_to_owner.contains_key(&collection_address) {
+            return Err(NftMarketplaceError(
+                "This collection address is not in the marketplace".to_owned(),
+            ));
+        }
+        if self.raffles.contains_key(&(collection_address, token_id)) {
+            return Err(NftMarketplaceError(
+                "This nft is already raffled".to_owned(),
+            ));
+        }
+        if ticket_price < self.config.minimum_transfer_value {
+            return Err(NftMarketplaceError(format!(
+                "The ticket price must be greater than existential deposit ({})",
+                self.config.minimum_transfer_value
+            )));
+        }
+        if max_tickets == 0 || max_tickets > MAX_TICKETS || min_tickets > max_tickets {
+            return Err(NftMarketplaceError(format!(
+                "The maximum number of tickets must be from 1 to {} and not less than the minimum",
+                MAX_TICKETS
+            )));
+        }
+        let current_time = exec::block_timestamp();
+        if ends_at <= current_time {
+            return Err(NftMarketplaceError(
+                "The end time of the raffle must be in the future".to_owned(),
+            ));
+        }
+
+        // check token info
+        let address_marketplace = exec::program_id();
+        let msg_src = msg::source();
+        let royalty_recipients = check_token_info(
+            &collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            &msg_src,
+            &address_marketplace,
+            self.capabilities(&collection_address).operator_approvals,
+        )
+        .await?;
+
+        // the token is kept by the marketplace until the raffle is drawn
+        transfer_from_token(
+            collection_address,
+            msg_src,
+            address_marketplace,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await?;
+
+        self.raffles.insert(
+            (collection_address, token_id),
+            Raffle {
+                owner: msg_src,
+                ticket_price,
+                max_tickets,
+                min_tickets,
+                ends_at,
+                royalty_recipients,
+                tickets: Vec::new(),
+            },
+        );
+
+        // the delayed message draws the raffle when it ends
+        let delay = (ends_at - current_time) / self.config.ms_in_block as u64 + 1;
+        msg::send_with_gas_delayed(
+            address_marketplace,
+            NftMarketplaceAction::DrawRaffle {
+                collection_address,
+                token_id,
+            },
+            self.config.gas_for_close_auction,
+            0,
+            delay.try_into().unwrap_or(u32::MAX),
+        )
+        .expect("Error in sending delayed message");
+
+        Ok(NftMarketplaceEvent::RaffleCreated {
+            collection_address,
+            token_id,
+            ticket_price,
+            max_tickets,
+            min_tickets,
+            ends_at,
+        })
+    }
+
+    pub fn buy_tickets(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+        count: u32,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let payment = msg::value();
+        let buyer = msg::source();
+        let raffle = if let Some(raffle) = self.raffles.get_mut(&(collection_address, token_id)) {
+            raffle
+        } else {
+            return Err(NftMarketplaceError("This nft is not raffled".to_owned()));
+        };
+        if raffle.ends_at <= exec::block_timestamp() {
+            return Err(NftMarketplaceError("The raffle has ended".to_owned()));
+        }
+        let available = raffle.max_tickets - raffle.tickets.len() as u32;
+        if count == 0 || count > available {
+            return Err(NftMarketplaceError(format!(
+                "The number of tickets must be from 1 to {}",
+                available
+            )));
+        }
+        if raffle.ticket_price.checked_mul(count as u128) != Some(payment) {
+            return Err(NftMarketplaceError(
+                "The attached value must be equal to the price of the tickets".to_owned(),
+            ));
+        }
+
+        let tickets = raffle.tickets.len() + count as usize;
+        raffle.tickets.resize(tickets, buyer);
+
+        Ok(NftMarketplaceEvent::TicketsBought {
+            collection_address,
+            token_id,
+            buyer,
+            count,
+        })
+    }
+
+    pub async fn draw_raffle(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        // the raffle is drawn by the delayed message, so that the caller can't choose the block
+        let msg_src = msg::source();
+        if msg_src != exec::program_id() && !self.admins.contains(&msg_src) {
+            return Err(NftMarketplaceError(
+                "Only program or admin can send this message.".to_owned(),
+            ));
+        }
+        let raffle = match self.raffles.get(&(collection_address, token_id)) {
+            Some(raffle) if raffle.ends_at <= exec::block_timestamp() => raffle.clone(),
+            _ => {
+                return Err(NftMarketplaceError(
+                    "This raffle does not exist or has not ended yet".to_owned(),
+                ))
+            }
+        };
+
+        // the raffle is taken out before the asynchronous call,
+        // so that it can't be drawn twice in the meantime
+        self.raffles.remove(&(collection_address, token_id));
+        let sold = raffle.tickets.len() as u32;
+        let winner = if sold == 0 || sold < raffle.min_tickets {
+            None
+        } else {
+            let (random, _) =
+                exec::random(random_subject(&raffle)).expect("Error in getting random number");
+            let index = u64::from_le_bytes(random[..8].try_into().expect("Can't be an error"))
+                % sold as u64;
+            Some(raffle.tickets[index as usize])
+        };
+
+        // the result of the draw is final, so the token waits for the recipient if the transfer fails
+        let to = winner.unwrap_or(raffle.owner);
+        if transfer_token(
+            collection_address,
+            to,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await
+        .is_err()
+        {
+            self.unreleased_tokens
+                .insert((collection_address, token_id), to);
+        }
+
+        if winner.is_some() {
+            // transfer value to token owner and percent to collection creator
+            currency_transfer(
+                &raffle.royalty_recipients,
+                raffle.owner,
+                raffle.ticket_price * sold as u128,
+                self.config.minimum_transfer_value,
+                0,
+            );
+        } else {
+            // not enough tickets were sold, so the buyers are refunded
+            let mut refunds: HashMap<ActorId, u128> = HashMap::new();
+            for buyer in raffle.tickets.iter() {
+                *refunds.entry(*buyer).or_default() += raffle.ticket_price;
+            }
+            for (buyer, value) in refunds {
+                // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+                msg::send_with_gas(buyer, "", 0, value).expect("Error in sending value");
+            }
+        }
+
+        Ok(NftMarketplaceEvent::RaffleDrawn {
+            collection_address,
+            token_id,
+            winner,
+        })
+    }
+}
+
+/// The subject of the random number depends on the raffle and the message that draws it,
+/// so it can't be prepared in advance
+fn random_subject(raffle: &Raffle) -> [u8; 32] {
+    let mut subject = [0; 32];
+    for (i, byte) in (msg::id(), raffle).encode().iter().enumerate() {
+        subject[i % 32] ^= byte;
+    }
+    subject
+}
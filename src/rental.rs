@@ -0,0 +1,189 @@
+use crate::nft_messages::*;
+use crate::payment::*;
+use crate::NftMarketplace;
+use gstd::{exec, msg, prelude::*, ActorId};
+use nft_marketplace_io::*;
+
+const MS_IN_DAY: u64 = 86_400_000;
+
+impl NftMarketplace {
+    pub async fn list_for_rent(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+        price_per_day: u128,
+        max_days: u32,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        if !self.collection_to_owner.contains_key(&collection_address) {
+            return Err(NftMarketplaceError(
+                "This collection address is not in the marketplace".to_owned(),
+            ));
+        }
+        if !self.capabilities(&collection_address).rentals {
+            return Err(NftMarketplaceError(
+                "This collection does not support rentals".to_owned(),
+            ));
+        }
+        if self.rentals.contains_key(&(collection_address, token_id)) {
+            return Err(NftMarketplaceError(
+                "This nft is already listed for rent".to_owned(),
+            ));
+        }
+        if price_per_day < self.config.minimum_transfer_value {
+            return Err(NftMarketplaceError(format!(
+                "The price must be greater than existential deposit ({})",
+                self.config.minimum_transfer_value
+            )));
+        }
+        if max_days == 0 {
+            return Err(NftMarketplaceError(
+                "The maximum rental period must be at least one day".to_owned(),
+            ));
+        }
+
+        // check token info
+        let address_marketplace = exec::program_id();
+        let msg_src = msg::source();
+        let royalty_recipients = check_token_info(
+            &collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            &msg_src,
+            &address_marketplace,
+            self.capabilities(&collection_address).operator_approvals,
+        )
+        .await?;
+
+        // the token stays with the owner, the collection itself keeps the rights of the user until they expire
+        self.rentals.insert(
+            (collection_address, token_id),
+            Rental {
+                owner: msg_src,
+                price_per_day,
+                max_days,
+                royalty_recipients,
+                user: None,
+            },
+        );
+
+        Ok(NftMarketplaceEvent::ListedForRent {
+            collection_address,
+            token_id,
+            price_per_day,
+            max_days,
+        })
+    }
+
+    pub async fn rent(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+        days: u32,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let payment = msg::value();
+        let user = msg::source();
+        let current_time = exec::block_timestamp();
+        let rental = if let Some(rental) = self.rentals.get(&(collection_address, token_id)) {
+            rental.clone()
+        } else {
+            return Err(NftMarketplaceError(
+                "This nft is not listed for rent".to_owned(),
+            ));
+        };
+        if rental
+            .user
+            .map_or(false, |(_, expires)| expires > current_time)
+        {
+            return Err(NftMarketplaceError("This nft is already rented".to_owned()));
+        }
+        if days == 0 || days > rental.max_days {
+            return Err(NftMarketplaceError(format!(
+                "The rental period must be from 1 to {} days",
+                rental.max_days
+            )));
+        }
+        let price = match rental.price_per_day.checked_mul(days as u128) {
+            Some(price) if price == payment => price,
+            _ => {
+                return Err(NftMarketplaceError(
+                    "The attached value must be equal to the rental price".to_owned(),
+                ))
+            }
+        };
+
+        let expires = current_time + days as u64 * MS_IN_DAY;
+        // the token is marked as rented before the asynchronous calls,
+        // so that it can't be rented twice in the meantime
+        if let Some(rental) = self.rentals.get_mut(&(collection_address, token_id)) {
+            rental.user = Some((user, expires));
+        }
+        // the owner could have transferred the token or revoked the approval since the listing
+        let result = match check_token_info(
+            &collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            &rental.owner,
+            &exec::program_id(),
+            self.capabilities(&collection_address).operator_approvals,
+        )
+        .await
+        {
+            Ok(_) => {
+                set_user(
+                    collection_address,
+                    token_id,
+                    user,
+                    expires,
+                    self.config.gas_for_transfer_token,
+                )
+                .await
+            }
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            if let Some(rental) = self.rentals.get_mut(&(collection_address, token_id)) {
+                rental.user = None;
+            }
+            return Err(error);
+        }
+
+        // transfer value to token owner and percent to collection creator
+        currency_transfer(
+            &rental.royalty_recipients,
+            rental.owner,
+            price,
+            self.config.minimum_transfer_value,
+            0,
+        );
+
+        Ok(NftMarketplaceEvent::Rented {
+            collection_address,
+            token_id,
+            user,
+            expires,
+        })
+    }
+
+    pub fn cancel_rent_listing(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        match self.rentals.get(&(collection_address, token_id)) {
+            Some(rental) if rental.owner == msg::source() => {}
+            _ => {
+                return Err(NftMarketplaceError(
+                    "This rental listing does not exist or you are not the owner of the token"
+                        .to_owned(),
+                ))
+            }
+        }
+        // the current user keeps the token until the rent is over, it is enforced by the collection
+        self.rentals.remove(&(collection_address, token_id));
+
+        Ok(NftMarketplaceEvent::RentListingCanceled {
+            collection_address,
+            token_id,
+        })
+    }
+}
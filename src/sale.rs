@@ -10,6 +10,7 @@ impl NftMarketplace {
         collection_address: ActorId,
         token_id: u64,
         price: u128,
+        custodial: bool,
     ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
         // check that this collection already exists in the marketplace
         if !self.collection_to_owner.contains_key(&collection_address) {
@@ -39,44 +40,45 @@ impl NftMarketplace {
         }
 
         // send a message to the nft contract to find out information about the token
-        let address_marketplace = exec::program_id();
-        let msg_src = msg::source();
-        let (collection_owner, royalty) = check_token_info(
-            &collection_address,
-            token_id,
-            self.config.gas_for_get_token_info,
-            &msg_src,
-            &address_marketplace,
-        )
-        .await?;
-
-        // send a message to the nft contract to transfer the token to the marketplace address 
+        // and transfer the token to the marketplace address
         // so that the token can be immediately transferred to the buyer upon purchase.
-        if let NftEvent::Transferred {
-            owner,
-            recipient: _,
-            token_id,
-        } = transfer_from_token(
-            collection_address,
-            msg_src,
-            address_marketplace,
-            token_id,
-            self.config.gas_for_transfer_token,
-        )
-        .await?
-        {
-            // if the transfer was successful, add information about the sale to the contract
-            self.sales.insert(
-                (collection_address, token_id),
-                NftInfoForSale {
-                    price,
-                    token_owner: owner,
-                    collection_owner,
-                    royalty,
-                },
-            );
+        // The token of a non-custodial listing stays with the owner,
+        // the marketplace keeps only the approval.
+        let msg_src = msg::source();
+        let royalty_recipients = if custodial {
+            self.take_token(&collection_address, token_id, &msg_src)
+                .await?
         } else {
-            return Err(NftMarketplaceError("Wrong received reply".to_owned()));
+            check_token_info(
+                &collection_address,
+                token_id,
+                self.config.gas_for_get_token_info,
+                &msg_src,
+                &exec::program_id(),
+                self.capabilities(&collection_address).operator_approvals,
+            )
+            .await?
+        };
+
+        // if the transfer was successful, add information about the sale to the contract
+        self.insert_sale(
+            (collection_address, token_id),
+            NftInfoForSale {
+                price,
+                token_owner: msg_src,
+                royalty_recipients,
+                referral_rate: None,
+                custodial,
+            },
+        );
+
+        // the new ask is executed at once if it crosses the highest collection offer
+        if let Some(trade) = self.match_ask(collection_address, token_id).await {
+            return Ok(NftMarketplaceEvent::OrdersMatched {
+                collection_address,
+                trades: vec![trade],
+                remaining_offer: None,
+            });
         }
 
         Ok(NftMarketplaceEvent::SaleNft {
@@ -94,6 +96,14 @@ impl NftMarketplace {
         // check that such a sale exists and that the sender of the message is the owner of the sale/nft
         if let Some(nft_info) = self.sales.get(&(collection_address, token_id)) {
             if nft_info.token_owner == msg::source() {
+                if !nft_info.custodial {
+                    // the token is still with its owner
+                    self.remove_sale(&(collection_address, token_id));
+                    return Ok(NftMarketplaceEvent::SaleNftCanceled {
+                        collection_address,
+                        token_id,
+                    });
+                }
                 // return the token to its owner
                 if let NftEvent::Transferred {
                     owner: _,
@@ -108,7 +118,7 @@ impl NftMarketplace {
                 .await?
                 {
                     // in case of successful token transfer, remove the sale from the marketplace
-                    self.sales.remove(&(collection_address, token_id));
+                    self.remove_sale(&(collection_address, token_id));
                 } else {
                     return Err(NftMarketplaceError("Wrong received reply".to_owned()));
                 }
@@ -131,45 +141,308 @@ impl NftMarketplace {
         &mut self,
         collection_address: ActorId,
         token_id: u64,
+        recipient: Option<ActorId>,
+        referrer: Option<ActorId>,
     ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
         let buyer = msg::source();
         self.check_sale(&collection_address, &token_id, &buyer)?;
+        let seller = self.sales[&(collection_address, token_id)].token_owner;
+        if let Err(error) = check_referrer(referrer, &buyer, recipient, &seller) {
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(buyer, "", 0, msg::value()).expect("Error in sending value");
+            return Err(error);
+        }
+
+        // the sale is taken out before the asynchronous calls,
+        // so that the token can't be bought twice in the meantime
+        let nft = self
+            .remove_sale(&(collection_address, token_id))
+            .expect("Can't be None");
 
-        // transfer the token to the buyer
-        transfer_token(
+        // transfer the token to the buyer or to the recipient of the gift
+        let current_owner = recipient.unwrap_or(buyer);
+        if let Err(error) = self
+            .transfer_sold_token(collection_address, token_id, &nft, current_owner)
+            .await
+        {
+            self.restore_sale((collection_address, token_id), nft).await;
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(buyer, "", 0, msg::value()).expect("Error in sending value");
+            return Err(error);
+        }
+
+        // transfer the referral fee to the referrer, the rest of the value to owner of token
+        // and percent to collection creator
+        let referral = referral_transfer(
+            referrer,
+            nft.price,
+            nft.referral_rate,
+            self.config.minimum_transfer_value,
+        );
+        currency_transfer_after_referral(
+            &nft.royalty_recipients,
+            nft.token_owner,
+            nft.price,
+            referral,
+            self.config.minimum_transfer_value,
+            self.gas_for_token_owner(&nft.token_owner),
+        );
+        Ok(NftMarketplaceEvent::NftSold {
             collection_address,
-            buyer,
             token_id,
-            self.config.gas_for_transfer_token,
-        )
-        .await?;
+            price: nft.price,
+            current_owner,
+            referral,
+        })
+    }
 
-        let nft = self
-            .sales
-            .get(&(collection_address, token_id))
-            .expect("Can't be None")
-            .clone();
+    pub fn set_referral_rate(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+        referral_rate: Option<u16>,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        if let Some(rate) = referral_rate {
+            check_referral_rate(rate)?;
+        }
+        let msg_src = msg::source();
+        let key = (collection_address, token_id);
+        if let Some(nft) = self.sales.get_mut(&key) {
+            if nft.token_owner != msg_src {
+                return Err(NftMarketplaceError(
+                    "Only the seller can set the referral rate".to_owned(),
+                ));
+            }
+            nft.referral_rate = referral_rate;
+        } else if let Some(auction) = self.auctions.get_mut(&key) {
+            if auction.owner != msg_src {
+                return Err(NftMarketplaceError(
+                    "Only the seller can set the referral rate".to_owned(),
+                ));
+            }
+            auction.referral_rate = referral_rate;
+        } else {
+            return Err(NftMarketplaceError(
+                "This token is not on sale or auction".to_owned(),
+            ));
+        }
+
+        Ok(NftMarketplaceEvent::ReferralRateSet {
+            collection_address,
+            token_id,
+            referral_rate,
+        })
+    }
+
+    pub async fn buy_many(
+        &mut self,
+        items: Vec<(ActorId, u64)>,
+        max_total: u128,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        if items.is_empty() {
+            return Err(NftMarketplaceError(
+                "The list of tokens to buy is empty".to_owned(),
+            ));
+        }
+        let payment = msg::value();
+        let buyer = msg::source();
+        // the buyer can't spend more than the attached value
+        let budget = payment.min(max_total);
+
+        let mut total = 0;
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            let result = self.buy_listed(item, &buyer, budget - total).await;
+            if let Ok(price) = result {
+                total += price;
+            }
+            results.push((item, result));
+        }
+
+        // the unspent value is returned to the buyer
+        if payment > total {
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(buyer, "", 0, payment - total).expect("Error in sending value");
+        }
+
+        Ok(NftMarketplaceEvent::NftsBought { results, total })
+    }
+
+    pub async fn buy_cheapest(
+        &mut self,
+        collection_address: ActorId,
+        count: u32,
+        max_total: u128,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        if !self.collection_to_owner.contains_key(&collection_address) {
+            return Err(NftMarketplaceError(
+                "This collection address is not in the marketplace".to_owned(),
+            ));
+        }
+        if count == 0 {
+            return Err(NftMarketplaceError(
+                "The number of tokens to buy must be greater than zero".to_owned(),
+            ));
+        }
+
+        let buyer = msg::source();
+        let items = self
+            .asks(&collection_address, None)
+            .filter(|(_, _, seller)| *seller != buyer)
+            .take(count as usize)
+            .map(|(_, token_id, _)| (collection_address, token_id))
+            .collect();
+
+        self.buy_many(items, max_total).await
+    }
+
+    /// Buys one token of the batch, the sale is taken out before the transfer,
+    /// so that the token can't be bought twice in the meantime
+    async fn buy_listed(
+        &mut self,
+        (collection_address, token_id): (ActorId, u64),
+        buyer: &ActorId,
+        budget: u128,
+    ) -> Result<u128, NftMarketplaceError> {
+        let nft = if let Some(nft) = self.sales.get(&(collection_address, token_id)) {
+            nft.clone()
+        } else {
+            return Err(NftMarketplaceError("This sale does not exist".to_owned()));
+        };
+        if nft.price > budget {
+            return Err(NftMarketplaceError(
+                "The price of the token exceeds the remaining budget".to_owned(),
+            ));
+        }
+
+        self.remove_sale(&(collection_address, token_id));
+        if let Err(error) = self
+            .transfer_sold_token(collection_address, token_id, &nft, *buyer)
+            .await
+        {
+            self.restore_sale((collection_address, token_id), nft).await;
+            return Err(error);
+        }
 
         // transfer value to owner of token and percent to collection creator
         currency_transfer(
-            nft.collection_owner,
+            &nft.royalty_recipients,
             nft.token_owner,
             nft.price,
-            nft.royalty,
             self.config.minimum_transfer_value,
+            self.gas_for_token_owner(&nft.token_owner),
         );
-        // remove the sale from the marketplace
-        self.sales
-            .remove(&(collection_address, token_id))
-            .expect("Can't be None");
-        Ok(NftMarketplaceEvent::NftSold {
+        Ok(nft.price)
+    }
+
+    pub async fn invalidate_listing(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let key = (collection_address, token_id);
+        let seller = match self.sales.get(&key) {
+            Some(nft) if !nft.custodial => nft.token_owner,
+            Some(_) => {
+                return Err(NftMarketplaceError(
+                    "The token of this listing is kept by the marketplace".to_owned(),
+                ))
+            }
+            None => return Err(NftMarketplaceError("This sale does not exist".to_owned())),
+        };
+
+        // the listing is valid as long as the seller owns the token and it is approved to the marketplace
+        if is_owned_and_approved(
+            &collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            &seller,
+            &exec::program_id(),
+            self.capabilities(&collection_address).operator_approvals,
+        )
+        .await?
+        {
+            return Err(NftMarketplaceError(
+                "The seller still owns and approves the token".to_owned(),
+            ));
+        }
+        // the token could have been bought or listed again in the meantime
+        if matches!(self.sales.get(&key), Some(nft) if !nft.custodial && nft.token_owner == seller)
+        {
+            self.remove_sale(&key);
+        }
+
+        Ok(NftMarketplaceEvent::ListingInvalidated {
             collection_address,
             token_id,
-            price: nft.price,
-            current_owner: buyer,
         })
     }
 
+    /// Transfers the sold token to the buyer. The token of a non-custodial listing
+    /// is transferred directly from the seller after checking the ownership and the approval again.
+    pub async fn transfer_sold_token(
+        &self,
+        collection_address: ActorId,
+        token_id: u64,
+        nft: &NftInfoForSale,
+        to: ActorId,
+    ) -> Result<(), NftMarketplaceError> {
+        if nft.custodial {
+            transfer_token(
+                collection_address,
+                to,
+                token_id,
+                self.config.gas_for_transfer_token,
+            )
+            .await?;
+            return Ok(());
+        }
+
+        check_token_info(
+            &collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            &nft.token_owner,
+            &exec::program_id(),
+            self.capabilities(&collection_address).operator_approvals,
+        )
+        .await?;
+        transfer_from_token(
+            collection_address,
+            nft.token_owner,
+            to,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the sale that was taken out before a failed transfer,
+    /// a non-custodial listing is dropped only if the seller no longer owns or approves the token
+    pub async fn restore_sale(&mut self, key: (ActorId, u64), nft: NftInfoForSale) {
+        if !nft.custodial {
+            let (collection_address, token_id) = key;
+            let revoked = matches!(
+                is_owned_and_approved(
+                    &collection_address,
+                    token_id,
+                    self.config.gas_for_get_token_info,
+                    &nft.token_owner,
+                    &exec::program_id(),
+                    self.capabilities(&collection_address).operator_approvals,
+                )
+                .await,
+                Ok(false)
+            );
+            // the seller could have listed the token again while waiting for the reply
+            if revoked || self.sales.contains_key(&key) {
+                return;
+            }
+        }
+        self.insert_sale(key, nft);
+    }
+
     fn check_sale(
         &self,
         collection_address: &ActorId,
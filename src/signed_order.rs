@@ -0,0 +1,127 @@
+use crate::nft_messages::*;
+use crate::payment::*;
+use crate::NftMarketplace;
+use gstd::{exec, msg, prelude::*, ActorId};
+use nft_marketplace_io::*;
+use schnorrkel::{PublicKey, Signature};
+
+const SIGNING_CONTEXT: &[u8] = b"substrate";
+
+impl NftMarketplace {
+    pub async fn buy_signed_order(
+        &mut self,
+        order: SignedOrder,
+        signature: [u8; 64],
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let payment = msg::value();
+        let buyer = msg::source();
+        if !self
+            .collection_to_owner
+            .contains_key(&order.collection_address)
+        {
+            return Err(NftMarketplaceError(
+                "This collection address is not in the marketplace".to_owned(),
+            ));
+        }
+        if order.price < self.config.minimum_transfer_value {
+            return Err(NftMarketplaceError(format!(
+                "The price must be greater than existential deposit ({})",
+                self.config.minimum_transfer_value
+            )));
+        }
+        if payment != order.price {
+            return Err(NftMarketplaceError(format!(
+                "The attached value must be equal to the price of the order ({})",
+                order.price
+            )));
+        }
+        if order.expires_at <= exec::block_timestamp() {
+            return Err(NftMarketplaceError("This order has expired".to_owned()));
+        }
+        if self
+            .used_order_nonces
+            .contains(&(order.seller, order.nonce))
+        {
+            return Err(NftMarketplaceError(
+                "This order has already been filled or canceled".to_owned(),
+            ));
+        }
+        check_signature(&order, &signature)?;
+
+        // the nonce is used before the asynchronous calls,
+        // so that the order can't be filled twice in the meantime
+        let key = (order.seller, order.nonce);
+        self.used_order_nonces.insert(key);
+        if let Err(error) = self.fill_signed_order(&order, buyer).await {
+            self.used_order_nonces.remove(&key);
+            return Err(error);
+        }
+
+        Ok(NftMarketplaceEvent::SignedOrderFilled { order, buyer })
+    }
+
+    pub fn cancel_order_nonce(
+        &mut self,
+        nonce: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let seller = msg::source();
+        if !self.used_order_nonces.insert((seller, nonce)) {
+            return Err(NftMarketplaceError(
+                "This nonce has already been used or canceled".to_owned(),
+            ));
+        }
+
+        Ok(NftMarketplaceEvent::OrderNonceCanceled { seller, nonce })
+    }
+
+    /// The seller must still own the token and approve it to the marketplace
+    async fn fill_signed_order(
+        &self,
+        order: &SignedOrder,
+        buyer: ActorId,
+    ) -> Result<(), NftMarketplaceError> {
+        let address_marketplace = exec::program_id();
+        let royalty_recipients = check_token_info(
+            &order.collection_address,
+            order.token_id,
+            self.config.gas_for_get_token_info,
+            &order.seller,
+            &address_marketplace,
+            self.capabilities(&order.collection_address)
+                .operator_approvals,
+        )
+        .await?;
+
+        transfer_from_token(
+            order.collection_address,
+            order.seller,
+            buyer,
+            order.token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await?;
+
+        // transfer value to token owner and percent to collection creator
+        currency_transfer(
+            &royalty_recipients,
+            order.seller,
+            order.price,
+            self.config.minimum_transfer_value,
+            0,
+        );
+        Ok(())
+    }
+}
+
+/// The account id of the seller is the sr25519 public key that signed the order
+fn check_signature(order: &SignedOrder, signature: &[u8; 64]) -> Result<(), NftMarketplaceError> {
+    let invalid_signature = || NftMarketplaceError("Invalid signature of the order".to_owned());
+    let seller: [u8; 32] = order.seller.into();
+    let public_key = PublicKey::from_bytes(&seller).map_err(|_| invalid_signature())?;
+    let signature = Signature::from_bytes(signature).map_err(|_| invalid_signature())?;
+    let message = (exec::program_id(), order).encode();
+
+    public_key
+        .verify_simple(SIGNING_CONTEXT, &message, &signature)
+        .map_err(|_| invalid_signature())
+}
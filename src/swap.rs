@@ -0,0 +1,280 @@
+use crate::nft_messages::*;
+use crate::NftMarketplace;
+use gstd::{collections::HashSet, exec, msg, prelude::*, ActorId};
+use nft_marketplace_io::*;
+
+impl NftMarketplace {
+    pub async fn propose_swap(
+        &mut self,
+        counterparty: ActorId,
+        proposer_items: Vec<(ActorId, u64)>,
+        counterparty_items: Vec<(ActorId, u64)>,
+        expires_at: Option<u64>,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let value = msg::value();
+        let proposer = msg::source();
+        if counterparty == proposer {
+            return Err(NftMarketplaceError(
+                "You can't propose a swap to yourself".to_owned(),
+            ));
+        }
+        if counterparty_items.is_empty() || (proposer_items.is_empty() && value == 0) {
+            return Err(NftMarketplaceError(
+                "Both sides of the swap must contain something".to_owned(),
+            ));
+        }
+        if value != 0 && value < self.config.minimum_transfer_value {
+            return Err(NftMarketplaceError(format!(
+                "The value must be greater than existential deposit ({})",
+                self.config.minimum_transfer_value
+            )));
+        }
+        self.check_swap_items(&proposer_items, &counterparty_items)?;
+        let current_time = exec::block_timestamp();
+        if let Some(expires_at) = expires_at {
+            if expires_at <= current_time {
+                return Err(NftMarketplaceError(
+                    "The expiration time of the swap must be in the future".to_owned(),
+                ));
+            }
+        }
+
+        // the proposer's tokens are kept by the marketplace until the swap is accepted or canceled
+        self.escrow_items(&proposer_items, &proposer).await?;
+
+        let swap_id = self.swap_nonce;
+        self.swap_nonce += 1;
+        let swap = Swap {
+            proposer,
+            counterparty,
+            proposer_items,
+            counterparty_items,
+            value,
+            expires_at,
+        };
+        self.swaps.insert(swap_id, swap.clone());
+
+        // the delayed message returns the tokens to the proposer if the swap is not accepted in time
+        if let Some(expires_at) = expires_at {
+            let delay = (expires_at - current_time) / self.config.ms_in_block as u64 + 1;
+            msg::send_with_gas_delayed(
+                exec::program_id(),
+                NftMarketplaceAction::ExpireSwap { swap_id },
+                self.config.gas_for_transfer_token * (swap.proposer_items.len() as u64 + 1),
+                0,
+                delay.try_into().unwrap_or(u32::MAX),
+            )
+            .expect("Error in sending delayed message");
+        }
+
+        Ok(NftMarketplaceEvent::SwapProposed { swap_id, swap })
+    }
+
+    pub async fn accept_swap(
+        &mut self,
+        swap_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let msg_src = msg::source();
+        let swap = if let Some(swap) = self.swaps.get(&swap_id) {
+            swap.clone()
+        } else {
+            return Err(NftMarketplaceError("This swap does not exist".to_owned()));
+        };
+        if swap.counterparty != msg_src {
+            return Err(NftMarketplaceError(
+                "Only the counterparty can accept the swap".to_owned(),
+            ));
+        }
+        if swap
+            .expires_at
+            .map_or(false, |expires_at| expires_at <= exec::block_timestamp())
+        {
+            return Err(NftMarketplaceError("This swap has expired".to_owned()));
+        }
+
+        // the swap is taken out before the asynchronous calls,
+        // so that it can't be canceled or accepted twice in the meantime
+        self.swaps.remove(&swap_id);
+        if let Err(error) = self.escrow_items(&swap.counterparty_items, &msg_src).await {
+            self.swaps.insert(swap_id, swap);
+            return Err(error);
+        }
+
+        // both sides are in the escrow, so the exchange can't be interrupted by the owners
+        self.release_items(&swap.proposer_items, &swap.counterparty)
+            .await;
+        self.release_items(&swap.counterparty_items, &swap.proposer)
+            .await;
+        if swap.value != 0 {
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(swap.counterparty, "", 0, swap.value)
+                .expect("Error in sending value");
+        }
+
+        Ok(NftMarketplaceEvent::SwapAccepted { swap_id })
+    }
+
+    pub async fn cancel_swap(
+        &mut self,
+        swap_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        match self.swaps.get(&swap_id) {
+            Some(swap) if swap.proposer == msg::source() => {}
+            _ => {
+                return Err(NftMarketplaceError(
+                    "This swap does not exist or you are not the proposer of the swap".to_owned(),
+                ))
+            }
+        }
+        self.return_swap(swap_id).await;
+
+        Ok(NftMarketplaceEvent::SwapCanceled { swap_id })
+    }
+
+    pub async fn expire_swap(
+        &mut self,
+        swap_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        match self.swaps.get(&swap_id) {
+            Some(swap)
+                if swap
+                    .expires_at
+                    .map_or(false, |expires_at| expires_at <= exec::block_timestamp()) => {}
+            _ => {
+                return Err(NftMarketplaceError(
+                    "This swap does not exist or has not expired yet".to_owned(),
+                ))
+            }
+        }
+        self.return_swap(swap_id).await;
+
+        Ok(NftMarketplaceEvent::SwapCanceled { swap_id })
+    }
+
+    /// Returns the escrowed tokens and value to the proposer
+    async fn return_swap(&mut self, swap_id: u64) {
+        let swap = self.swaps.remove(&swap_id).expect("Can't be None");
+        self.release_items(&swap.proposer_items, &swap.proposer)
+            .await;
+        if swap.value != 0 {
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(swap.proposer, "", 0, swap.value).expect("Error in sending value");
+        }
+    }
+
+    fn check_swap_items(
+        &self,
+        proposer_items: &[(ActorId, u64)],
+        counterparty_items: &[(ActorId, u64)],
+    ) -> Result<(), NftMarketplaceError> {
+        let mut items = HashSet::new();
+        for item in proposer_items.iter().chain(counterparty_items.iter()) {
+            if !self.collection_to_owner.contains_key(&item.0) {
+                return Err(NftMarketplaceError(
+                    "This collection address is not in the marketplace".to_owned(),
+                ));
+            }
+            if !items.insert(*item) {
+                return Err(NftMarketplaceError(
+                    "The same token can't be in the swap twice".to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks and transfers the tokens to the marketplace,
+    /// if one of them fails, the already transferred ones are returned to the owner
+    async fn escrow_items(
+        &mut self,
+        items: &[(ActorId, u64)],
+        owner: &ActorId,
+    ) -> Result<(), NftMarketplaceError> {
+        let address_marketplace = exec::program_id();
+        for (index, (collection_address, token_id)) in items.iter().enumerate() {
+            let result = match check_token_info(
+                collection_address,
+                *token_id,
+                self.config.gas_for_get_token_info,
+                owner,
+                &address_marketplace,
+                self.capabilities(collection_address).operator_approvals,
+            )
+            .await
+            {
+                Ok(_) => {
+                    transfer_from_token(
+                        *collection_address,
+                        *owner,
+                        address_marketplace,
+                        *token_id,
+                        self.config.gas_for_transfer_token,
+                    )
+                    .await
+                }
+                Err(error) => Err(error),
+            };
+            if let Err(error) = result {
+                self.release_items(&items[..index], owner).await;
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Transfers the escrowed tokens to the recipient, the tokens that fail to be transferred
+    /// are recorded, so that anyone can retry the transfer with `release_token`
+    async fn release_items(&mut self, items: &[(ActorId, u64)], to: &ActorId) {
+        for (collection_address, token_id) in items.iter() {
+            if transfer_token(
+                *collection_address,
+                *to,
+                *token_id,
+                self.config.gas_for_transfer_token,
+            )
+            .await
+            .is_err()
+            {
+                self.unreleased_tokens
+                    .insert((*collection_address, *token_id), *to);
+            }
+        }
+    }
+
+    pub async fn release_token(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        // the token is taken out before the asynchronous call,
+        // so that it can't be released twice in the meantime
+        let to = if let Some(to) = self
+            .unreleased_tokens
+            .remove(&(collection_address, token_id))
+        {
+            to
+        } else {
+            return Err(NftMarketplaceError(
+                "This token is not waiting to be released".to_owned(),
+            ));
+        };
+        if let Err(error) = transfer_token(
+            collection_address,
+            to,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await
+        {
+            self.unreleased_tokens
+                .insert((collection_address, token_id), to);
+            return Err(error);
+        }
+
+        Ok(NftMarketplaceEvent::TokenReleased {
+            collection_address,
+            token_id,
+            to,
+        })
+    }
+}
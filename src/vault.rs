@@ -0,0 +1,233 @@
+use crate::nft_messages::*;
+use crate::NftMarketplace;
+use gstd::{exec, msg, prelude::*, prog::ProgramGenerator, ActorId, CodeId};
+use nft_marketplace_io::*;
+use vault_io::VaultInit;
+
+impl NftMarketplace {
+    pub fn set_vault_code(
+        &mut self,
+        code_id: CodeId,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        self.check_admin()?;
+        self.vault_code_id = Some(code_id);
+        Ok(NftMarketplaceEvent::VaultCodeSet { code_id })
+    }
+
+    pub async fn create_vault(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+        total_shares: u128,
+        reserve_price: u128,
+        buyout_duration_ms: u64,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let code_id = if let Some(code_id) = self.vault_code_id {
+            code_id
+        } else {
+            return Err(NftMarketplaceError(
+                "The vault code has not been set yet".to_owned(),
+            ));
+        };
+        if !self.collection_to_owner.contains_key(&collection_address) {
+            return Err(NftMarketplaceError(
+                "This collection address is not in the marketplace".to_owned(),
+            ));
+        }
+        if total_shares == 0 {
+            return Err(NftMarketplaceError(
+                "The number of shares must be greater than zero".to_owned(),
+            ));
+        }
+        if reserve_price < self.config.minimum_transfer_value {
+            return Err(NftMarketplaceError(format!(
+                "The reserve price must be greater than existential deposit ({})",
+                self.config.minimum_transfer_value
+            )));
+        }
+
+        // check token info
+        let address_marketplace = exec::program_id();
+        let msg_src = msg::source();
+        check_token_info(
+            &collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            &msg_src,
+            &address_marketplace,
+            self.capabilities(&collection_address).operator_approvals,
+        )
+        .await?;
+
+        // the token is taken before the vault is deployed, so that no vault is left without a token
+        transfer_from_token(
+            collection_address,
+            msg_src,
+            address_marketplace,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await?;
+
+        let vault_init = VaultInit {
+            marketplace: address_marketplace,
+            depositor: msg_src,
+            collection_address,
+            token_id,
+            total_shares,
+            reserve_price,
+            buyout_duration_ms,
+            gas_for_transfer_token: self.config.gas_for_transfer_token,
+            gas_for_listing: self.config.gas_for_get_token_info
+                + self.config.gas_for_transfer_token
+                + self.config.gas_for_close_auction,
+            gas_for_close_buyout: self.config.gas_for_close_auction,
+            ms_in_block: self.config.ms_in_block,
+        };
+        let vault_address = match ProgramGenerator::create_program_bytes_with_gas_for_reply(
+            code_id,
+            vault_init.encode(),
+            self.config.gas_for_creation,
+            0,
+            0,
+        ) {
+            Ok(future) => future.await.ok().map(|(vault_address, _)| vault_address),
+            Err(_) => None,
+        };
+        let vault_address = if let Some(vault_address) = vault_address {
+            vault_address
+        } else {
+            if transfer_token(
+                collection_address,
+                msg_src,
+                token_id,
+                self.config.gas_for_transfer_token,
+            )
+            .await
+            .is_err()
+            {
+                self.unreleased_tokens
+                    .insert((collection_address, token_id), msg_src);
+            }
+            return Err(NftMarketplaceError(
+                "The vault program was not initialized".to_owned(),
+            ));
+        };
+
+        self.vaults
+            .insert(vault_address, (collection_address, token_id));
+        // the vault is already deployed, so the token waits for it if the transfer fails
+        if transfer_token(
+            collection_address,
+            vault_address,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await
+        .is_err()
+        {
+            self.unreleased_tokens
+                .insert((collection_address, token_id), vault_address);
+        }
+
+        Ok(NftMarketplaceEvent::VaultCreated {
+            vault_address,
+            collection_address,
+            token_id,
+        })
+    }
+
+    /// The vault reports that its token has been sold by a buyout or through the marketplace,
+    /// it no longer holds the token and only pays out the proceeds
+    pub fn close_vault(&mut self) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let vault_address = msg::source();
+        let Some((collection_address, token_id)) = self.vaults.remove(&vault_address) else {
+            return Err(NftMarketplaceError(
+                "This vault is not in the marketplace".to_owned(),
+            ));
+        };
+
+        Ok(NftMarketplaceEvent::VaultClosed {
+            vault_address,
+            collection_address,
+            token_id,
+        })
+    }
+
+    /// Checks the token and transfers it from the owner to the marketplace.
+    /// A vault transfers its token to the marketplace by itself before listing,
+    /// so only the information about the token is checked.
+    pub async fn take_token(
+        &self,
+        collection_address: &ActorId,
+        token_id: u64,
+        owner: &ActorId,
+    ) -> Result<Vec<(ActorId, u16)>, NftMarketplaceError> {
+        let address_marketplace = exec::program_id();
+        if self.vaults.get(owner) == Some(&(*collection_address, token_id)) {
+            return check_escrowed_token(
+                collection_address,
+                token_id,
+                self.config.gas_for_get_token_info,
+                &address_marketplace,
+            )
+            .await;
+        }
+
+        let token_info = check_token_info(
+            collection_address,
+            token_id,
+            self.config.gas_for_get_token_info,
+            owner,
+            &address_marketplace,
+            self.capabilities(collection_address).operator_approvals,
+        )
+        .await?;
+
+        transfer_from_token(
+            *collection_address,
+            *owner,
+            address_marketplace,
+            token_id,
+            self.config.gas_for_transfer_token,
+        )
+        .await?;
+
+        Ok(token_info)
+    }
+
+    /// Vaults are programs that record the received proceeds,
+    /// so the payment to a vault is sent with gas
+    pub fn gas_for_token_owner(&self, token_owner: &ActorId) -> u64 {
+        if self.vaults.contains_key(token_owner) {
+            self.config.gas_for_transfer_token
+        } else {
+            0
+        }
+    }
+
+    /// Returns the token to the vault if the vault's listing has failed
+    pub async fn check_vault_listing(
+        &mut self,
+        collection_address: ActorId,
+        token_id: u64,
+        result: Result<NftMarketplaceEvent, NftMarketplaceError>,
+    ) -> Result<NftMarketplaceEvent, NftMarketplaceError> {
+        let msg_src = msg::source();
+        if result.is_err()
+            && self.vaults.get(&msg_src) == Some(&(collection_address, token_id))
+            && !self.sales.contains_key(&(collection_address, token_id))
+            && !self.auctions.contains_key(&(collection_address, token_id))
+        {
+            // the vault may not have transferred the token, so the result of the transfer is not important
+            let _ = transfer_token(
+                collection_address,
+                msg_src,
+                token_id,
+                self.config.gas_for_transfer_token,
+            )
+            .await;
+        }
+        result
+    }
+}
@@ -31,10 +31,12 @@ async fn create_test() -> Result<()> {
             user_mint_limit: 3.into(),
             // time_for_change,
             time_to_action,
-            transferable: true,
+            royalty: 0,
+            payment_for_mint: 0,
+            transferable: Some(0),
             approvable: true,
             burnable: true,
-            sellable: true,
+            sellable: Some(0),
             attendable: true,
         },
         img_links,
@@ -30,6 +30,7 @@ async fn create_test() -> Result<()> {
             collection_tags: vec!["tag1".to_string()],
             additional_links: None,
             royalty: 0,
+            royalty_recipients: vec![],
             payment_for_mint: 0,
             user_mint_limit: 3.into(),
             tokens_limit: Some(500),
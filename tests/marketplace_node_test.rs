@@ -10,7 +10,6 @@ use nft_io::{
 };
 use nft_marketplace_io::*;
 
-const USERS: &[u64] = &[5, 6, 7, 8];
 pub const USERS_STR: &[&str] = &["//John", "//Mike", "//Dan"];
 const ALICE: [u8; 32] = [
     212, 53, 147, 199, 21, 253, 211, 28, 97, 20, 26, 189, 4, 169, 159, 214, 130, 44, 133, 88, 133,
@@ -57,6 +56,7 @@ async fn create_test() -> Result<()> {
         gas_for_close_auction: 10_000_000_000,
         gas_for_delete_collection: 5_000_000_000,
         gas_for_get_token_info: 5_000_000_000,
+        gas_for_cleanup_offers: 5_000_000_000,
         time_between_create_collections: 3_600_000, // 1 hour in milliseconds
         minimum_transfer_value: 10_000_000_000_000,
         ms_in_block: 3_000,
@@ -120,7 +120,7 @@ async fn create_test() -> Result<()> {
 
     // Successful creation of a new collection
     let init_nft_payload = NftInit {
-        collection_owner: USERS[0].into(),
+        collection_owner: ALICE.into(),
         config: Config {
             name: "User Collection".to_string(),
             description: "User Collection".to_string(),
@@ -129,6 +129,7 @@ async fn create_test() -> Result<()> {
             collection_tags: vec!["tag1".to_string()],
             additional_links: None,
             royalty: 0,
+            royalty_recipients: vec![],
             user_mint_limit: Some(3),
             payment_for_mint: 0,
             transferable: Some(0),
@@ -177,7 +178,7 @@ async fn create_test() -> Result<()> {
         .await
         .expect("Unexpected invalid state.");
 
-    assert_eq!(state.collection_owner, USERS[0].into(), "Wrong Admin");
+    assert_eq!(state.collection_owner, ALICE.into(), "Wrong Admin");
     println!("NFT Collection STATE: {:?}", state);
 
     let gas_info = api
@@ -218,6 +219,7 @@ async fn sale_test() -> Result<()> {
         gas_for_close_auction: 10_000_000_000,
         gas_for_delete_collection: 5_000_000_000,
         gas_for_get_token_info: 5_000_000_000,
+        gas_for_cleanup_offers: 5_000_000_000,
         time_between_create_collections: 3_600_000, // 1 hour in milliseconds
         minimum_transfer_value: 10_000_000_000_000,
         ms_in_block: 3_000,
@@ -290,6 +292,7 @@ async fn sale_test() -> Result<()> {
             collection_tags: vec!["tag1".to_string()],
             additional_links: None,
             royalty: 1_000,
+            royalty_recipients: vec![],
             user_mint_limit: Some(3),
             payment_for_mint: 0,
             transferable: Some(0),
@@ -436,6 +439,8 @@ async fn sale_test() -> Result<()> {
     let buy_payload = NftMarketplaceAction::BuyNft {
         collection_address: address_nft,
         token_id: 0,
+        recipient: None,
+        referrer: None,
     };
     let gas_info = api
         .calculate_handle_gas(None, program_id, buy_payload.encode(), 0, true)
@@ -476,6 +481,7 @@ async fn auction_test() -> Result<()> {
         gas_for_close_auction: 10_000_000_000,
         gas_for_delete_collection: 5_000_000_000,
         gas_for_get_token_info: 5_000_000_000,
+        gas_for_cleanup_offers: 5_000_000_000,
         time_between_create_collections: 3_600_000, // 1 hour in milliseconds
         minimum_transfer_value: 10_000_000_000_000,
         ms_in_block: 3_000,
@@ -548,6 +554,7 @@ async fn auction_test() -> Result<()> {
             collection_tags: vec!["tag1".to_string()],
             additional_links: None,
             royalty: 1_000,
+            royalty_recipients: vec![],
             user_mint_limit: Some(3),
             payment_for_mint: 0,
             transferable: Some(0),
@@ -692,6 +699,8 @@ async fn auction_test() -> Result<()> {
     let add_bid_payload = NftMarketplaceAction::AddBid {
         collection_address: address_nft,
         token_id: 0,
+        recipient: None,
+        referrer: None,
     };
     let gas_info = api
         .calculate_handle_gas(None, program_id, add_bid_payload.encode(), 0, true)
@@ -741,6 +750,7 @@ async fn offer_test() -> Result<()> {
         gas_for_close_auction: 10_000_000_000,
         gas_for_delete_collection: 5_000_000_000,
         gas_for_get_token_info: 5_000_000_000,
+        gas_for_cleanup_offers: 5_000_000_000,
         time_between_create_collections: 3_600_000, // 1 hour in milliseconds
         minimum_transfer_value: 10_000_000_000_000,
         ms_in_block: 3_000,
@@ -813,6 +823,7 @@ async fn offer_test() -> Result<()> {
             collection_tags: vec!["tag1".to_string()],
             additional_links: None,
             royalty: 1_000,
+            royalty_recipients: vec![],
             user_mint_limit: Some(3),
             payment_for_mint: 0,
             transferable: Some(0),
@@ -905,6 +916,9 @@ async fn offer_test() -> Result<()> {
     let create_offer_payload = NftMarketplaceAction::CreateOffer {
         collection_address: address_nft,
         token_id: 0,
+        expires_at: None,
+        recipient: None,
+        referrer: None,
     };
 
     let gas_info = client
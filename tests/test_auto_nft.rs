@@ -1,137 +1,206 @@
-// use crate::utils::{add_new_collection, create_collection, init_marketplace};
-// use utils::prelude::*;
-// mod utils;
-// use gtest::Program;
-// use nft_marketplace_io::*;
-
-// use auto_changed_nft_io::{
-//     Action, AutoNftAction, AutoNftEvent, AutoNftInit, AutoNftState, Config,
-//     StateQuery as StateQueryNft, StateReply as StateReplyNft,
-// };
-// const USERS: &[u64] = &[5, 6, 7, 8];
-
-// #[test]
-// fn successful_auto_changed() {
-//     let sys = utils::initialize_system();
-//     init_marketplace(&sys);
-//     let marketplace = sys.get_program(1);
-//     let nft_collection_code_id =
-//         sys.submit_code("target/wasm32-unknown-unknown/debug/auto_changed_nft.opt.wasm");
-
-//     let state_reply = marketplace
-//         .read_state(StateQuery::CollectionsInfo)
-//         .expect("Unexpected invalid state.");
-//     if let StateReply::CollectionsInfo(state) = state_reply {
-//         assert!(state.is_empty(), "Collection info should be empty");
-//         println!("Collection info: {:?}", state);
-//     }
-//     // Successful addition of a new collection
-//     let res = add_new_collection(
-//         &marketplace,
-//         ADMINS[0],
-//         nft_collection_code_id.into_bytes().into(),
-//     );
-//     assert!(!res.main_failed());
-//     let state_reply = marketplace
-//         .read_state(StateQuery::CollectionsInfo)
-//         .expect("Unexpected invalid state.");
-//     if let StateReply::CollectionsInfo(state) = state_reply {
-//         assert!(!state.is_empty(), "Collection info shouldn't be empty");
-//         println!("Collection info: {:?}", state);
-//     }
-
-//     let links: Vec<String> = (0..4).map(|i| format!("Img-{}", i)).collect();
-//     let img_links: Vec<(Vec<String>, u32)> = (0..10).map(|i| (links.clone(), 1 as u32)).collect();
-
-//     // let time_for_change = 5;
-//     let time_to_action = vec![
-//         (3, Action::ChangeImg),
-//         (6, Action::ChangeImg),
-//         (9, Action::ChangeImg),
-//     ];
-//     // Successful creation of a new collection
-//     let init_nft_payload = AutoNftInit {
-//         owner: USERS[0].into(),
-//         config: Config {
-//             name: "User Collection".to_string(),
-//             description: "User Collection".to_string(),
-//             collection_banner: "Collection banner".to_string(),
-//             collection_tags: vec!["tag1".to_string()],
-//             user_mint_limit: 3.into(),
-//             // time_for_change,
-//             time_to_action,
-//             transferable: true,
-//             approvable: true,
-//             burnable: true,
-//             sellable: true,
-//             attendable: true,
-//         },
-//         img_links,
-//     };
-//     let res = create_collection(&marketplace, USERS[0], 1, init_nft_payload.encode());
-//     assert!(!res.main_failed());
-//     let result = &res.decoded_log::<Result<NftMarketplaceEvent, NftMarketplaceError>>()[0];
-//     println!("RES: {:?}", result);
-//     let state_reply = marketplace
-//         .read_state(StateQuery::AllCollections)
-//         .expect("Unexpected invalid state.");
-//     let address_nft = if let StateReply::AllCollections(state) = state_reply {
-//         assert!(!state.is_empty(), "Collections shouldn't be empty");
-//         println!("Collections: {:?}", state);
-//         state[0].0
-//     } else {
-//         assert!(false, "Unexpected StateReply variant");
-//         0.into()
-//     };
-
-//     let address_nft: [u8; 32] = address_nft.into();
-//     let nft_collection = sys.get_program(address_nft);
-//     let state_reply = nft_collection
-//         .read_state(StateQueryNft::All)
-//         .expect("Unexpected invalid state.");
-//     if let StateReplyNft::All(state) = state_reply {
-//         assert_eq!(state.admins[0], USERS[0].into(), "Wrong Admin");
-//         println!("Collection NFT info: {:?}", state);
-//     }
-
-//     // Successful mint NFT in the new collection
-//     let res = nft_collection.send(USERS[1], AutoNftAction::Mint);
-//     assert!(!res.main_failed());
-//     // let state_reply = nft_collection
-//     //     .read_state(StateQueryNft::All)
-//     //     .expect("Unexpected invalid state.");
-//     // if let StateReplyNft::All(state) = state_reply {
-//     //     println!("Collection NFT info: {:?}", state);
-//     //     assert_eq!(state.admins[0], USERS[0].into(), "Wrong Admin");
-//     //     let (owner, token_id) = state.owners.get(0).expect("Can't be None");
-//     //     assert_eq!(*owner, USERS[1].into(), "Wrong owner");
-//     //     assert_eq!(*token_id, vec![0], "Wrong token id");
-//     // }
-//     let state = get_state(&nft_collection).unwrap();
-//     assert_eq!(state.tokens.get(0).unwrap().1.media_url.0, 0);
-//     sys.spend_blocks(1);
-//     let state = get_state(&nft_collection).unwrap();
-//     assert_eq!(state.tokens.get(0).unwrap().1.media_url.0, 1);
-//     sys.spend_blocks(1);
-//     let state = get_state(&nft_collection).unwrap();
-//     assert_eq!(state.tokens.get(0).unwrap().1.media_url.0, 2);
-//     sys.spend_blocks(1);
-//     let state = get_state(&nft_collection).unwrap();
-//     assert_eq!(state.tokens.get(0).unwrap().1.media_url.0, 3);
-//     // sys.spend_blocks(2);
-//     // let state = get_state(&nft_collection).unwrap();
-//     // assert_eq!(state.tokens.get(0).unwrap().1.media_url.0, 3);
-//     // sys.spend_blocks(interval_block);
-//     // let state = get_state(&nft_collection).unwrap();
-//     // assert_eq!(state.tokens.get(0).unwrap().1.media_url.0, 0);
-// }
-
-// fn get_state(nft_collection: &Program) -> Option<AutoNftState> {
-//     let state_reply = nft_collection
-//         .read_state(StateQueryNft::All)
-//         .expect("Unexpected invalid state.");
-//     if let StateReplyNft::All(state) = state_reply {
-//         return Some(state);
-//     }
-//     None
-// }
+use crate::utils::*;
+use utils::prelude::*;
+mod utils;
+use gtest::Program;
+use nft_marketplace_io::*;
+
+use auto_changed_nft_io::{
+    Action, AutoNftAction, AutoNftInit, AutoNftState, Config, StateQuery as StateQueryNft,
+    StateReply as StateReplyNft,
+};
+const USERS: &[u64] = &[5, 6, 7, 8];
+
+#[test]
+fn successful_auto_changed() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/auto_changed_nft.opt.wasm");
+
+    // Successful addition of a new collection
+    let name_auto_nft = "Auto NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_auto_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    let time_to_action = vec![
+        (3, Action::ChangeImg),
+        (6, Action::ChangeImg),
+        (9, Action::ChangeImg),
+    ];
+    // Successful creation of a new collection
+    let init_nft_payload = get_init_auto_nft_payload(USERS[0].into(), 0, 0, time_to_action);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_auto_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let address_nft = get_collection_address(&marketplace);
+    let address_nft: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft);
+    let state = get_state(&nft_collection).expect("Unexpected invalid state.");
+    assert_eq!(state.admins[0], USERS[0].into(), "Wrong Admin");
+    assert_eq!(state.collection_owner, USERS[0].into());
+
+    // Successful mint NFT in the new collection, the image changes by the rules of the collection
+    let res = nft_collection.send(USERS[1], AutoNftAction::Mint);
+    assert!(!res.main_failed());
+    let state = get_state(&nft_collection).expect("Unexpected invalid state.");
+    assert_eq!(state.tokens.get(0).unwrap().1.media_url.0, 0);
+    for image in 1..4 {
+        sys.spend_blocks(1);
+        let state = get_state(&nft_collection).expect("Unexpected invalid state.");
+        assert_eq!(state.tokens.get(0).unwrap().1.media_url.0, image);
+    }
+}
+
+#[test]
+fn auto_nft_trade() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/auto_changed_nft.opt.wasm");
+
+    let name_auto_nft = "Auto NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_auto_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection with the royalty of 10% and the payment for mint
+    let royalty = 1_000;
+    let payment_for_mint = 10_000_000_000_000;
+    let init_nft_payload =
+        get_init_auto_nft_payload(USERS[0].into(), royalty, payment_for_mint, vec![]);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_auto_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let address_nft = get_collection_address(&marketplace);
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // The payment for mint goes to the collection owner
+    sys.mint_to(USERS[1], payment_for_mint);
+    let res = nft_collection.send_with_value(USERS[1], AutoNftAction::Mint, payment_for_mint);
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[0]), payment_for_mint, "Wrong balance");
+
+    // The collection can't be deleted while it has tokens
+    let res = delete_collection(&marketplace, USERS[0], address_nft);
+    assert!(!res.main_failed());
+    assert!(check_payload(0, &res, "Removal denied".to_string()));
+
+    // Successful approve, sale and purchase of the token
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        AutoNftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    let price = 150_000_000_000_000;
+    let res = sale(&marketplace, USERS[1], address_nft, 0, price);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(state.sales.len(), 1);
+        assert_eq!(
+            state.sales[0].1.royalty_recipients,
+            vec![(USERS[0].into(), royalty)]
+        );
+    }
+
+    sys.mint_to(USERS[2], price);
+    let res = buy(&marketplace, USERS[2], address_nft, 0, price);
+    assert!(!res.main_failed());
+    let state = get_state(&nft_collection).expect("Unexpected invalid state.");
+    assert_eq!(state.tokens.get(0).unwrap().1.owner, USERS[2].into());
+
+    let percent_to_collection_owner = price * royalty as u128 / 10_000;
+    sys.claim_value_from_mailbox(USERS[0]);
+    let balance = sys.balance_of(USERS[0]);
+    assert_eq!(
+        balance,
+        payment_for_mint + percent_to_collection_owner,
+        "Wrong balance"
+    );
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        price - percent_to_collection_owner,
+        "Wrong balance"
+    );
+}
+
+fn get_init_auto_nft_payload(
+    owner: ActorId,
+    royalty: u16,
+    payment_for_mint: u128,
+    time_to_action: Vec<(u32, Action)>,
+) -> AutoNftInit {
+    let links: Vec<String> = (0..4).map(|i| format!("Img-{}", i)).collect();
+    let img_links: Vec<(Vec<String>, u32)> = (0..10).map(|_| (links.clone(), 1)).collect();
+
+    AutoNftInit {
+        owner,
+        config: Config {
+            name: "User Collection".to_string(),
+            description: "User Collection".to_string(),
+            collection_banner: "Collection banner".to_string(),
+            collection_tags: vec!["tag1".to_string()],
+            user_mint_limit: 3.into(),
+            time_to_action,
+            royalty,
+            payment_for_mint,
+            transferable: Some(0),
+            approvable: true,
+            burnable: true,
+            sellable: Some(0),
+            attendable: true,
+        },
+        img_links,
+    }
+}
+
+fn get_collection_address(marketplace: &Program) -> ActorId {
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    }
+}
+
+fn get_state(nft_collection: &Program) -> Option<AutoNftState> {
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        return Some(state);
+    }
+    None
+}
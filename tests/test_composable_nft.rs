@@ -1,4 +1,7 @@
-use crate::utils::{add_new_collection, buy, create_collection, init_marketplace, sale};
+use crate::utils::{
+    accept_trait_offer, add_new_collection, buy, check_payload, create_collection,
+    create_trait_offer, init_marketplace, list_for_rent, sale,
+};
 use utils::prelude::*;
 mod utils;
 use composable_nft_io::{
@@ -61,6 +64,7 @@ fn successful_basics() {
             collection_tags: vec!["tag1".to_string()],
             additional_links: None,
             royalty: 0,
+            royalty_recipients: vec![],
             payment_for_mint: 0,
             user_mint_limit: 3.into(),
             tokens_limit: Some(500),
@@ -176,6 +180,7 @@ fn composable_sale_success() {
             collection_tags: vec!["tag1".to_string()],
             additional_links: None,
             royalty: 0,
+            royalty_recipients: vec![],
             payment_for_mint: 0,
             user_mint_limit: 3.into(),
             tokens_limit: Some(500),
@@ -259,3 +264,237 @@ fn composable_sale_success() {
         assert_eq!(token.1.owner, USERS[2].into())
     }
 }
+
+#[test]
+fn composable_trait_offer() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/composable_nft.opt.wasm");
+
+    let name_composable_nft = "Composable NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_composable_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    let mut img_links: Vec<Vec<String>> = Vec::with_capacity(4);
+    for k in 0..3 {
+        let inner_vec: Vec<String> = (0..10).map(|i| format!("img{}-{}", k, i)).collect();
+        img_links.push(inner_vec);
+    }
+
+    let init_nft_payload = ComposableNftInit {
+        owner: USERS[0].into(),
+        config: Config {
+            name: "User Collection".to_string(),
+            description: "User Collection".to_string(),
+            collection_banner: "Collection banner".to_string(),
+            collection_logo: "Collection logo".to_string(),
+            collection_tags: vec!["tag1".to_string()],
+            additional_links: None,
+            royalty: 0,
+            royalty_recipients: vec![],
+            payment_for_mint: 0,
+            user_mint_limit: 3.into(),
+            tokens_limit: Some(500),
+            transferable: Some(0),
+            sellable: Some(0),
+        },
+        img_links,
+    };
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_composable_nft,
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint and approve two NFTs with different combinations
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for (token_id, combination) in [vec![0, 1, 2], vec![0, 5, 2]].into_iter().enumerate() {
+        let res = nft_collection.send(USERS[1], ComposableNftAction::Mint { combination });
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            ComposableNftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id: token_id as u64,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+
+    // Offer for any token with the option 5 in the layer 1
+    let offer_price = 150_000_000_000_000;
+    sys.mint_to(USERS[2], offer_price);
+    let res = create_trait_offer(&marketplace, USERS[2], address_nft, 1, 5, offer_price);
+    assert!(!res.main_failed());
+
+    let offer = TraitOffer {
+        collection_address: address_nft,
+        layer: 1,
+        option: 5,
+        creator: USERS[2].into(),
+    };
+
+    // the token doesn't match the offer
+    let res = accept_trait_offer(&marketplace, USERS[1], offer.clone(), 0);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The token does not match the offer".to_string()
+    ));
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(state.trait_offers, vec![(offer.clone(), offer_price)]);
+    }
+
+    // Successful accept
+    let res = accept_trait_offer(&marketplace, USERS[1], offer, 1);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.trait_offers.is_empty());
+    }
+
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(balance, offer_price, "Wrong balance");
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let (_, token) = state
+            .tokens
+            .iter()
+            .find(|(token_id, _)| *token_id == 1)
+            .expect("Can't be None");
+        assert_eq!(token.owner, USERS[2].into());
+    }
+}
+
+#[test]
+fn composable_capabilities() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/composable_nft.opt.wasm");
+
+    let name_composable_nft = "Composable NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_composable_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    let img_links: Vec<Vec<String>> = (0..3)
+        .map(|k| (0..10).map(|i| format!("img{}-{}", k, i)).collect())
+        .collect();
+    let init_nft_payload = ComposableNftInit {
+        owner: USERS[0].into(),
+        config: Config {
+            name: "User Collection".to_string(),
+            description: "User Collection".to_string(),
+            collection_banner: "Collection banner".to_string(),
+            collection_logo: "Collection logo".to_string(),
+            collection_tags: vec!["tag1".to_string()],
+            additional_links: None,
+            royalty: 0,
+            royalty_recipients: vec![],
+            payment_for_mint: 0,
+            user_mint_limit: 3.into(),
+            tokens_limit: Some(500),
+            transferable: Some(0),
+            sellable: Some(0),
+        },
+        img_links,
+    };
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_composable_nft,
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    // The marketplace keeps the features reported by the collection in the handshake
+    let state_reply = marketplace
+        .read_state(StateQuery::GetCollectionInfo(address_nft))
+        .expect("Unexpected invalid state.");
+    if let StateReply::CollectionInfo(Some(info)) = state_reply {
+        assert_eq!(
+            info.capabilities,
+            Capabilities {
+                version: PROTOCOL_VERSION,
+                operator_approvals: true,
+                rentals: false,
+                token_combinations: true,
+            }
+        );
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+    }
+
+    // Composable tokens can't be rented out
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+    let res = nft_collection.send(
+        USERS[1],
+        ComposableNftAction::Mint {
+            combination: vec![0, 0, 0],
+        },
+    );
+    assert!(!res.main_failed());
+    let res = list_for_rent(
+        &marketplace,
+        USERS[1],
+        address_nft,
+        0,
+        10_000_000_000_000,
+        3,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This collection does not support rentals".to_string()
+    ));
+}
@@ -73,6 +73,7 @@ fn create_success() {
         None,
         None,
         None,
+        None,
         Some(7_200_000),
         Some(11_000_000_000_000),
         None,
@@ -188,6 +189,7 @@ fn create_failures() {
         None,
         None,
         None,
+        None,
         Some(7_200_000),
         Some(11_000_000_000_000),
         None,
@@ -209,6 +211,37 @@ fn create_failures() {
 
     let init_nft_payload = get_init_nft_payload(USERS[0].into(), 0, Some(3), 0);
 
+    // The collection can't be created for another owner
+    let res = create_collection(
+        &marketplace,
+        USERS[1],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The collection owner must be the creator of the collection".to_string()
+    ));
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert!(state.collection_to_owner.is_empty());
+    assert!(state.unsupported_collections.is_empty());
+
+    // The init payload must start with the collection owner
+    let res = create_collection(&marketplace, USERS[1], name_simple_nft.clone(), vec![]);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The init payload must start with the collection owner".to_string()
+    ));
+
     // Сan only create one collection per hour
     let res = create_collection(
         &marketplace,
@@ -1148,7 +1181,7 @@ fn offer_success() {
 
     let offer_price = 150_000_000_000_000;
     sys.mint_to(USERS[2], offer_price);
-    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price);
+    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price, None);
     assert!(!res.main_failed());
 
     let state_reply = marketplace
@@ -1158,7 +1191,7 @@ fn offer_success() {
         println!("STATE: {:?}", state);
         let creator: ActorId = USERS[2].into();
         assert_eq!(creator, state.offers[0].0.creator);
-        assert_eq!(offer_price, state.offers[0].1);
+        assert_eq!(offer_price, state.offers[0].1.price);
     }
 
     let res = accept_offer(&marketplace, USERS[1], address_nft, 0, USERS[2].into());
@@ -1258,7 +1291,7 @@ fn offer_failures() {
     // NonFungibleToken: token does not exist
     let offer_price = 150_000_000_000_000;
     sys.mint_to(USERS[2], offer_price);
-    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price);
+    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price, None);
     assert!(!res.main_failed());
     assert!(check_payload(
         0,
@@ -1269,7 +1302,7 @@ fn offer_failures() {
     // wrong collection address
     let offer_price = 150_000_000_000_000;
     sys.mint_to(USERS[2], offer_price);
-    let res = create_offer(&marketplace, USERS[2], 1.into(), 0, offer_price);
+    let res = create_offer(&marketplace, USERS[2], 1.into(), 0, offer_price, None);
     assert!(!res.main_failed());
     assert!(check_payload(
         0,
@@ -1306,7 +1339,7 @@ fn offer_failures() {
 
     let offer_price = 150_000_000_000_000;
     sys.mint_to(USERS[2], offer_price);
-    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price);
+    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price, None);
     assert!(!res.main_failed());
 
     let state_reply = marketplace
@@ -1316,16 +1349,16 @@ fn offer_failures() {
         println!("STATE: {:?}", state);
         let creator: ActorId = USERS[2].into();
         assert_eq!(creator, state.offers[0].0.creator);
-        assert_eq!(offer_price, state.offers[0].1);
+        assert_eq!(offer_price, state.offers[0].1.price);
     }
 
-    let res = accept_offer(&marketplace, USERS[1], address_nft, 0, USERS[2].into());
+    let res = accept_offer(&marketplace, USERS[3], address_nft, 0, USERS[2].into());
     let result = &res.decoded_log::<Result<NftMarketplaceEvent, NftMarketplaceError>>();
     println!("RES: {:?}", result);
     assert!(check_payload(
         0,
         &res,
-        "This token is on sale, cancel the sale if you wish to accept the offer".to_string()
+        "Only the seller can accept the offer for the token on sale".to_string()
     ));
     assert!(!res.main_failed());
 
@@ -1410,3 +1443,3616 @@ fn offer_failures() {
         assert_eq!(token.1.owner, USERS[2].into())
     }
 }
+
+#[test]
+fn offer_expiration() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let royalty = 1_000;
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), royalty, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint NFT in the new collection
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+
+    // Successful approve NFT in the collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // expiration time in the past
+    let offer_price = 150_000_000_000_000;
+    sys.mint_to(USERS[2], offer_price);
+    let res = create_offer(
+        &marketplace,
+        USERS[2],
+        address_nft,
+        0,
+        offer_price,
+        Some(sys.block_timestamp()),
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The expiration time of the offer must be in the future".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), offer_price, "Wrong balance");
+
+    // Successful offer with expiration
+    let duration_ms = 10_000;
+    let duration_blocks = duration_ms / 3000 + 1;
+    let expires_at = sys.block_timestamp() + duration_ms as u64;
+    let res = create_offer(
+        &marketplace,
+        USERS[2],
+        address_nft,
+        0,
+        offer_price,
+        Some(expires_at),
+    );
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(state.offers[0].1.expires_at, Some(expires_at));
+        assert_eq!(offer_price, state.offers[0].1.price);
+    }
+
+    // the delayed message returns the value of the expired offer
+    sys.spend_blocks(duration_blocks);
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.offers.is_empty());
+    }
+    sys.claim_value_from_mailbox(USERS[2]);
+    let balance = sys.balance_of(USERS[2]);
+    assert_eq!(balance, offer_price, "Wrong balance");
+
+    let res = accept_offer(&marketplace, USERS[1], address_nft, 0, USERS[2].into());
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This offer does not exist".to_string()
+    ));
+
+    // With a smaller block time in the config the value is returned later than the offer expires,
+    // so the expired offer is still stored but can't be accepted
+    let res = update_config(
+        &marketplace,
+        ADMINS[0],
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        Some(1_000),
+    );
+    assert!(!res.main_failed());
+    let duration_ms = 30_000;
+    let expires_at = sys.block_timestamp() + duration_ms;
+    sys.mint_to(USERS[2], offer_price);
+    let res = create_offer(
+        &marketplace,
+        USERS[2],
+        address_nft,
+        0,
+        offer_price,
+        Some(expires_at),
+    );
+    assert!(!res.main_failed());
+    sys.spend_blocks(12);
+    let res = accept_offer(&marketplace, USERS[1], address_nft, 0, USERS[2].into());
+    assert!(!res.main_failed());
+    assert!(check_payload(0, &res, "This offer has expired".to_string()));
+
+    // Anyone can return the value of the expired offer
+    let offer = Offer {
+        collection_address: address_nft,
+        token_id: 0,
+        creator: USERS[2].into(),
+    };
+    let res = marketplace.send(
+        USERS[3],
+        NftMarketplaceAction::CleanupExpiredOffers {
+            offers: vec![offer.clone()],
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(res.contains(&(
+        USERS[3],
+        Ok::<NftMarketplaceEvent, NftMarketplaceError>(
+            NftMarketplaceEvent::ExpiredOffersCleanedUp {
+                offers: vec![offer]
+            }
+        )
+        .encode()
+    )));
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert!(state.offers.is_empty());
+    assert_eq!(sys.balance_of(USERS[2]), 2 * offer_price, "Wrong balance");
+
+    // The delayed message of the replaced offer doesn't return the value of the new offer
+    let expires_at = sys.block_timestamp() + duration_ms;
+    let res = create_offer(
+        &marketplace,
+        USERS[2],
+        address_nft,
+        0,
+        offer_price,
+        Some(expires_at),
+    );
+    assert!(!res.main_failed());
+    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price, None);
+    assert!(!res.main_failed());
+    sys.spend_blocks(32);
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert_eq!(state.offers.len(), 1);
+    assert_eq!(state.offers[0].1.expires_at, None);
+    assert_eq!(sys.balance_of(USERS[2]), offer_price, "Wrong balance");
+}
+
+#[test]
+fn collection_offer() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let royalty = 1_000;
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), royalty, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint and approve two NFTs in the new collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for token_id in 0..2 {
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+
+    let offer_price = 150_000_000_000_000;
+    let quantity = 2;
+    let value = offer_price * quantity as u128;
+
+    // the value is not a multiple of the quantity
+    sys.mint_to(USERS[2], value + 1);
+    let res = create_collection_offer(&marketplace, USERS[2], address_nft, quantity, value + 1);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The value must be a multiple of the quantity".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), value + 1, "Wrong balance");
+
+    // Successful collection offer
+    let res = create_collection_offer(&marketplace, USERS[2], address_nft, quantity, value);
+    assert!(!res.main_failed());
+
+    // Partial fill
+    let res = accept_collection_offer(&marketplace, USERS[1], address_nft, USERS[2].into(), 0);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        let creator: ActorId = USERS[2].into();
+        assert_eq!(creator, state.collection_offers[0].0.creator);
+        assert_eq!(offer_price, state.collection_offers[0].1.price);
+        assert_eq!(1, state.collection_offers[0].1.quantity);
+    }
+
+    // the token is already transferred to the creator of the offer
+    let res = accept_collection_offer(&marketplace, USERS[1], address_nft, USERS[2].into(), 0);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only the owner of the token can perform this action.".to_string()
+    ));
+
+    // Full fill
+    let res = accept_collection_offer(&marketplace, USERS[1], address_nft, USERS[2].into(), 1);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.collection_offers.is_empty());
+    }
+
+    let percent_to_collection_owner = offer_price * royalty as u128 / 10_000;
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        2 * (offer_price - percent_to_collection_owner),
+        "Wrong balance"
+    );
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        for (_, token) in state.tokens.iter() {
+            assert_eq!(token.owner, USERS[2].into())
+        }
+    }
+}
+
+#[test]
+fn counter_offer_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let royalty = 1_000;
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), royalty, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint NFT in the new collection
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+
+    // Successful approve NFT in the collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let offer_price = 150_000_000_000_000;
+    sys.mint_to(USERS[2], offer_price);
+    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price, None);
+    assert!(!res.main_failed());
+
+    let offer = Offer {
+        collection_address: address_nft,
+        token_id: 0,
+        creator: USERS[2].into(),
+    };
+    let counter_price = 200_000_000_000_000;
+
+    // Only the owner of the token can make a counter-offer
+    let res = counter_offer(&marketplace, USERS[3], offer.clone(), counter_price);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only the owner of the token can perform this action.".to_string()
+    ));
+
+    // Successful counter-offer
+    let res = counter_offer(&marketplace, USERS[1], offer.clone(), counter_price);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(
+            state.counter_offers,
+            vec![(offer.clone(), (USERS[1].into(), counter_price))]
+        );
+    }
+
+    // Successful accept of the counter-offer with the top-up
+    let top_up = counter_price - offer_price;
+    sys.mint_to(USERS[2], top_up);
+    let res = accept_counter_offer(&marketplace, USERS[2], offer, top_up);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.offers.is_empty());
+        assert!(state.counter_offers.is_empty());
+    }
+
+    let percent_to_collection_owner = counter_price * royalty as u128 / 10_000;
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        counter_price - percent_to_collection_owner,
+        "Wrong balance"
+    );
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let token = state.tokens.get(0).expect("Can't be None");
+        assert_eq!(token.1.owner, USERS[2].into())
+    }
+}
+
+#[test]
+fn counter_offer_failures() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 0);
+    mint_approved_token(&sys, address_nft, USERS[1], 0);
+
+    let offer_price = 150_000_000_000_000;
+    let counter_price = 200_000_000_000_000;
+    let top_up = counter_price - offer_price;
+    sys.mint_to(USERS[2], offer_price + top_up);
+    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price, None);
+    assert!(!res.main_failed());
+    let offer = Offer {
+        collection_address: address_nft,
+        token_id: 0,
+        creator: USERS[2].into(),
+    };
+    let res = counter_offer(&marketplace, USERS[1], offer.clone(), counter_price);
+    assert!(!res.main_failed());
+
+    // The top-up is returned if it doesn't match the difference between the prices
+    let res = accept_counter_offer(&marketplace, USERS[2], offer.clone(), top_up - 1);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The attached value must be equal to the difference between the prices".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), top_up, "Wrong balance");
+
+    // The top-up is returned if the sender is not the creator of the offer
+    sys.mint_to(USERS[3], top_up);
+    let res = accept_counter_offer(&marketplace, USERS[3], offer.clone(), top_up);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only the creator of the offer can accept the counter-offer".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[3]), top_up, "Wrong balance");
+
+    // The top-up is returned and the offer is restored if the token can't be transferred
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Transfer {
+            to: USERS[3].into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = accept_counter_offer(&marketplace, USERS[2], offer.clone(), top_up);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only the owner of the token can perform this action.".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), top_up, "Wrong balance");
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert_eq!(
+        state.offers,
+        vec![(
+            offer.clone(),
+            OfferInfo {
+                price: offer_price,
+                expires_at: None,
+                recipient: None,
+                referrer: None,
+            }
+        )]
+    );
+    assert_eq!(
+        state.counter_offers,
+        vec![(offer, (USERS[1].into(), counter_price))]
+    );
+}
+
+#[test]
+fn offer_on_listed_token() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let royalty = 1_000;
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), royalty, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint NFT in the new collection
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+
+    // Successful approve NFT in the collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let price = 200_000_000_000_000;
+    let res = sale(&marketplace, USERS[1], address_nft, 0, price);
+    assert!(!res.main_failed());
+
+    let offer_price = 150_000_000_000_000;
+    sys.mint_to(USERS[2], offer_price);
+    let res = create_offer(&marketplace, USERS[2], address_nft, 0, offer_price, None);
+    assert!(!res.main_failed());
+
+    // the seller accepts the offer without canceling the sale
+    let res = accept_offer(&marketplace, USERS[1], address_nft, 0, USERS[2].into());
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.sales.is_empty());
+        assert!(state.offers.is_empty());
+    }
+
+    let percent_to_collection_owner = offer_price * royalty as u128 / 10_000;
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        offer_price - percent_to_collection_owner,
+        "Wrong balance"
+    );
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let token = state.tokens.get(0).expect("Can't be None");
+        assert_eq!(token.1.owner, USERS[2].into())
+    }
+}
+
+#[test]
+fn swap_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_000, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // USERS[1] owns the token 0 and USERS[2] owns the token 1
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for (token_id, user) in [USERS[1], USERS[2]].into_iter().enumerate() {
+        let res = nft_collection.send(user, nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            user,
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id: token_id as u64,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+
+    // The swap with yourself is rejected and the value is returned
+    let value = 50_000_000_000_000;
+    sys.mint_to(USERS[1], value);
+    let res = propose_swap(
+        &marketplace,
+        USERS[1],
+        USERS[1].into(),
+        vec![(address_nft, 0)],
+        vec![(address_nft, 1)],
+        value,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "You can't propose a swap to yourself".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[1]), value, "Wrong balance");
+
+    // Successful swap proposal with additional value
+    let res = propose_swap(
+        &marketplace,
+        USERS[1],
+        USERS[2].into(),
+        vec![(address_nft, 0)],
+        vec![(address_nft, 1)],
+        value,
+    );
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        let (swap_id, swap) = state.swaps.get(0).expect("Can't be None");
+        assert_eq!(*swap_id, 0);
+        assert_eq!(swap.proposer, USERS[1].into());
+        assert_eq!(swap.value, value);
+    }
+
+    // Only the counterparty can accept the swap
+    let res = accept_swap(&marketplace, USERS[3], 0);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only the counterparty can accept the swap".to_string()
+    ));
+
+    // Successful accept
+    let res = accept_swap(&marketplace, USERS[2], 0);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.swaps.is_empty());
+    }
+
+    sys.claim_value_from_mailbox(USERS[2]);
+    let balance = sys.balance_of(USERS[2]);
+    assert_eq!(balance, value, "Wrong balance");
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        for (token_id, token) in state.tokens.iter() {
+            let expected_owner = if *token_id == 0 { USERS[2] } else { USERS[1] };
+            assert_eq!(token.owner, expected_owner.into());
+        }
+    }
+}
+
+#[test]
+fn swap_release_failure() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 0);
+    mint_approved_token(&sys, address_nft, USERS[1], 0);
+    mint_approved_token(&sys, address_nft, USERS[1], 1);
+    mint_approved_token(&sys, address_nft, USERS[2], 2);
+
+    let value = 100_000_000_000_000;
+    sys.mint_to(USERS[1], value);
+    let res = propose_swap(
+        &marketplace,
+        USERS[1],
+        USERS[2].into(),
+        vec![(address_nft, 0), (address_nft, 1)],
+        vec![(address_nft, 2)],
+        value,
+    );
+    assert!(!res.main_failed());
+
+    // The collection rejects the transfers out of the escrow because there is not enough gas,
+    // the swap is still canceled and the tokens wait to be released
+    let res = update_config(
+        &marketplace,
+        ADMINS[0],
+        None,
+        Some(1_000_000),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(!res.main_failed());
+    let res = marketplace.send(USERS[1], NftMarketplaceAction::CancelSwap { swap_id: 0 });
+    assert!(!res.main_failed());
+    assert!(res.contains(&(
+        USERS[1],
+        Ok::<NftMarketplaceEvent, NftMarketplaceError>(NftMarketplaceEvent::SwapCanceled {
+            swap_id: 0
+        })
+        .encode()
+    )));
+    assert_eq!(sys.balance_of(USERS[1]), value, "Wrong balance");
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert!(state.swaps.is_empty());
+    let mut unreleased_tokens = state.unreleased_tokens;
+    unreleased_tokens.sort_by_key(|((_, token_id), _)| *token_id);
+    assert_eq!(
+        unreleased_tokens,
+        vec![
+            ((address_nft, 0), USERS[1].into()),
+            ((address_nft, 1), USERS[1].into())
+        ]
+    );
+
+    // A failed release keeps the record
+    let release = NftMarketplaceAction::ReleaseToken {
+        collection_address: address_nft,
+        token_id: 0,
+    };
+    let res = marketplace.send_bytes(USERS[3], release.encode());
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The collection failed to transfer the token".to_string()
+    ));
+
+    // Anyone can release the token when the collection accepts the transfer
+    let res = update_config(
+        &marketplace,
+        ADMINS[0],
+        None,
+        Some(5_000_000_000),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(!res.main_failed());
+    let res = marketplace.send_bytes(USERS[3], release.encode());
+    assert!(!res.main_failed());
+    assert!(res.contains(&(
+        USERS[3],
+        Ok::<NftMarketplaceEvent, NftMarketplaceError>(NftMarketplaceEvent::TokenReleased {
+            collection_address: address_nft,
+            token_id: 0,
+            to: USERS[1].into(),
+        })
+        .encode()
+    )));
+    let res = marketplace.send_bytes(USERS[3], release.encode());
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This token is not waiting to be released".to_string()
+    ));
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+    let StateReplyNft::All(state) = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    let (_, token) = state
+        .tokens
+        .iter()
+        .find(|(token_id, _)| *token_id == 0)
+        .expect("Can't be None");
+    assert_eq!(token.owner, USERS[1].into());
+}
+
+#[test]
+fn rent_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let royalty = 1_000;
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), royalty, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint NFT in the new collection
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+
+    // Successful approve NFT in the collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // Successful listing for rent
+    let price_per_day = 50_000_000_000_000;
+    let res = list_for_rent(&marketplace, USERS[1], address_nft, 0, price_per_day, 7);
+    assert!(!res.main_failed());
+
+    // Wrong rental period
+    let days = 2;
+    let price = price_per_day * days as u128;
+    sys.mint_to(USERS[2], 2 * price);
+    let res = rent(&marketplace, USERS[2], address_nft, 0, 8, price);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The rental period must be from 1 to 7 days".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), 2 * price, "Wrong balance");
+
+    // Successful rent
+    let res = rent(&marketplace, USERS[2], address_nft, 0, days, price);
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        let (_, rental) = state.rentals.get(0).expect("Can't be None");
+        let (user, _) = rental.user.expect("Can't be None");
+        assert_eq!(user, USERS[2].into());
+    }
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let (token_id, (user, _)) = state.users.get(0).expect("Can't be None");
+        assert_eq!(*token_id, 0);
+        assert_eq!(*user, USERS[2].into());
+    }
+
+    let percent_to_collection_owner = price * royalty as u128 / 10_000;
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        price - percent_to_collection_owner,
+        "Wrong balance"
+    );
+
+    // The listing is canceled, but the token stays with the owner and the user keeps the rights
+    let res = marketplace.send(
+        USERS[1],
+        NftMarketplaceAction::CancelRentListing {
+            collection_address: address_nft,
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // The transfer doesn't take away the rights of the user
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Transfer {
+            to: USERS[3].into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // The new owner can't replace the user until the rent is over
+    let res = nft_collection.send(
+        USERS[3],
+        nft_io::NftAction::SetUser {
+            token_id: 0,
+            user: USERS[3].into(),
+            expires: sys.block_timestamp() + 1_000_000,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The token is already used by another user".to_string()
+    ));
+
+    let StateReplyNft::All(state) = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    let (_, token) = state
+        .tokens
+        .iter()
+        .find(|(token_id, _)| *token_id == 0)
+        .expect("Can't be None");
+    assert_eq!(token.owner, USERS[3].into());
+    let (_, (user, _)) = state.users.get(0).expect("Can't be None");
+    assert_eq!(*user, USERS[2].into());
+}
+
+#[test]
+fn rent_failures() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 1_000);
+    mint_approved_token(&sys, address_nft, USERS[1], 0);
+
+    let price_per_day = 50_000_000_000_000;
+    let res = list_for_rent(&marketplace, USERS[1], address_nft, 0, price_per_day, 7);
+    assert!(!res.main_failed());
+
+    // Wrong value
+    sys.mint_to(USERS[2], 2 * price_per_day);
+    let res = rent(&marketplace, USERS[2], address_nft, 0, 1, 2 * price_per_day);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The attached value must be equal to the rental price".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), 2 * price_per_day, "Wrong balance");
+
+    // The owner revoked the approval after the listing
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::RevokeApproval { token_id: 0 });
+    assert!(!res.main_failed());
+
+    let res = rent(&marketplace, USERS[2], address_nft, 0, 1, price_per_day);
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[2]), 2 * price_per_day, "Wrong balance");
+
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    let (_, rental) = state.rentals.get(0).expect("Can't be None");
+    assert!(rental.user.is_none());
+}
+
+#[test]
+fn loan_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_000, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint NFT in the new collection
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+
+    // Successful approve NFT in the collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // Successful loan request
+    let amount = 100_000_000_000_000;
+    let interest = 10_000_000_000_000;
+    let res = request_loan(
+        &marketplace,
+        USERS[1],
+        address_nft,
+        0,
+        amount,
+        interest,
+        60_000,
+    );
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::OpenLoans)
+        .expect("Unexpected invalid state.");
+    if let StateReply::OpenLoans(loans) = state_reply {
+        let (loan_id, loan) = loans.get(0).expect("Can't be None");
+        assert_eq!(*loan_id, 0);
+        assert_eq!(loan.borrower, USERS[1].into());
+    }
+
+    // Successful funding
+    sys.mint_to(USERS[2], amount);
+    let res = fund_loan(&marketplace, USERS[2], 0, amount);
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::ActiveLoans)
+        .expect("Unexpected invalid state.");
+    if let StateReply::ActiveLoans(loans) = state_reply {
+        let (_, loan) = loans.get(0).expect("Can't be None");
+        let (lender, _) = loan.lender.expect("Can't be None");
+        assert_eq!(lender, USERS[2].into());
+    }
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(balance, amount, "Wrong balance");
+
+    // The lender can't claim the collateral before the loan term expires
+    let res = marketplace.send(
+        USERS[2],
+        NftMarketplaceAction::ClaimCollateral { loan_id: 0 },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The loan term has not expired yet".to_string()
+    ));
+
+    // Successful repayment
+    sys.mint_to(USERS[1], interest);
+    let res = repay_loan(&marketplace, USERS[1], 0, amount + interest);
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.loans.is_empty());
+    }
+    sys.claim_value_from_mailbox(USERS[2]);
+    let balance = sys.balance_of(USERS[2]);
+    assert_eq!(balance, amount + interest, "Wrong balance");
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let token = state.tokens.get(0).expect("Can't be None");
+        assert_eq!(token.1.owner, USERS[1].into())
+    }
+}
+
+#[test]
+fn loan_failures() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 1_000);
+    mint_approved_token(&sys, address_nft, USERS[1], 0);
+
+    let amount = 100_000_000_000_000;
+    let interest = 10_000_000_000_000;
+    let res = request_loan(
+        &marketplace,
+        USERS[1],
+        address_nft,
+        0,
+        amount,
+        interest,
+        60_000,
+    );
+    assert!(!res.main_failed());
+
+    // Wrong amount
+    sys.mint_to(USERS[2], 2 * amount);
+    let res = fund_loan(&marketplace, USERS[2], 0, 2 * amount);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The attached value must be equal to the loan amount".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), 2 * amount, "Wrong balance");
+
+    let res = fund_loan(&marketplace, USERS[2], 0, amount);
+    assert!(!res.main_failed());
+
+    // The loan has already been funded
+    sys.mint_to(USERS[3], amount);
+    let res = fund_loan(&marketplace, USERS[3], 0, amount);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This loan request does not exist or has already been funded".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[3]), amount, "Wrong balance");
+
+    // Repayment without interest
+    let res = repay_loan(&marketplace, USERS[1], 0, amount);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The attached value must be equal to the loan amount with interest".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[1]), amount, "Wrong balance");
+
+    // The loan term has expired
+    sys.spend_blocks(21);
+    sys.mint_to(USERS[1], interest);
+    let res = repay_loan(&marketplace, USERS[1], 0, amount + interest);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The loan term has expired".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[1]), amount + interest, "Wrong balance");
+
+    // The lender claims the collateral
+    let res = marketplace.send(
+        USERS[2],
+        NftMarketplaceAction::ClaimCollateral { loan_id: 0 },
+    );
+    assert!(!res.main_failed());
+    assert!(res.contains(&(
+        USERS[2],
+        Ok::<NftMarketplaceEvent, NftMarketplaceError>(NftMarketplaceEvent::CollateralClaimed {
+            loan_id: 0
+        })
+        .encode()
+    )));
+
+    let address_nft: [u8; 32] = address_nft.into();
+    let StateReplyNft::All(state) = sys
+        .get_program(address_nft)
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    let (_, token) = state
+        .tokens
+        .iter()
+        .find(|(token_id, _)| *token_id == 0)
+        .expect("Can't be None");
+    assert_eq!(token.owner, USERS[2].into());
+}
+
+#[test]
+fn vault_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+    let vault_code_id = sys.submit_code("target/wasm32-unknown-unknown/debug/vault.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Only the admin can set the vault code
+    let res = marketplace.send(
+        USERS[0],
+        NftMarketplaceAction::SetVaultCode {
+            code_id: vault_code_id.into_bytes().into(),
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only admin can send this message".to_string()
+    ));
+    let res = marketplace.send(
+        ADMINS[0],
+        NftMarketplaceAction::SetVaultCode {
+            code_id: vault_code_id.into_bytes().into(),
+        },
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_000, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint NFT in the new collection
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+
+    // Successful approve NFT in the collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // Successful vault creation
+    let reserve_price = 100_000_000_000_000;
+    let res = marketplace.send(
+        USERS[1],
+        NftMarketplaceAction::CreateVault {
+            collection_address: address_nft,
+            token_id: 0,
+            total_shares: 100,
+            reserve_price,
+            buyout_duration_ms: 60_000,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    let address_vault = if let StateReply::All(state) = state_reply {
+        let (address_vault, token) = state.vaults.get(0).expect("Can't be None");
+        assert_eq!(*token, (address_nft, 0));
+        *address_vault
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+    let address_vault_2: [u8; 32] = address_vault.into();
+    let vault = sys.get_program(address_vault_2);
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let token = state.tokens.get(0).expect("Can't be None");
+        assert_eq!(token.1.owner, address_vault)
+    }
+
+    // The depositor shares part of the token
+    let res = vault.send(
+        USERS[1],
+        vault_io::VaultAction::TransferShares {
+            to: USERS[2].into(),
+            amount: 40,
+        },
+    );
+    assert!(!res.main_failed());
+    let state_reply = vault
+        .read_state(vault_io::StateQuery::Shares(USERS[2].into()))
+        .expect("Unexpected invalid state.");
+    if let vault_io::StateReply::Shares(shares) = state_reply {
+        assert_eq!(shares, 40);
+    }
+
+    // The holder of the majority of the shares lists the token for sale
+    let price = 150_000_000_000_000;
+    let res = vault.send(
+        USERS[1],
+        vault_io::VaultAction::VoteForListing {
+            listing: vault_io::Listing::Sale { price },
+        },
+    );
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        let (_, sale) = state.sales.get(0).expect("Can't be None");
+        assert_eq!(sale.token_owner, address_vault);
+        assert_eq!(sale.price, price);
+    }
+
+    // Successful buy
+    sys.mint_to(USERS[3], price);
+    let res = buy(&marketplace, USERS[3], address_nft, 0, price);
+    assert!(!res.main_failed());
+
+    let state_reply = vault
+        .read_state(vault_io::StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let vault_io::StateReply::All(state) = state_reply {
+        assert_eq!(state.status, vault_io::VaultStatus::Sold);
+        // the royalty is paid to the creator of the collection
+        assert_eq!(state.proceeds, price * 9 / 10);
+    }
+
+    // Share holders redeem the proceeds
+    let res = vault.send(USERS[2], vault_io::VaultAction::Redeem);
+    assert!(!res.main_failed());
+    sys.claim_value_from_mailbox(USERS[2]);
+    let balance = sys.balance_of(USERS[2]);
+    assert_eq!(balance, price * 9 / 10 * 40 / 100, "Wrong balance");
+
+    let res = vault.send(USERS[1], vault_io::VaultAction::Redeem);
+    assert!(!res.main_failed());
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(balance, price * 9 / 10 * 60 / 100, "Wrong balance");
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let token = state.tokens.get(0).expect("Can't be None");
+        assert_eq!(token.1.owner, USERS[3].into())
+    }
+
+    // The vault is closed after the sale, so the buyer can list the token again
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert!(state.vaults.is_empty());
+    let res = marketplace.send(USERS[3], NftMarketplaceAction::CloseVault);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This vault is not in the marketplace".to_string()
+    ));
+
+    let res = nft_collection.send(
+        USERS[3],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = sale(&marketplace, USERS[3], address_nft, 0, price);
+    assert!(!res.main_failed());
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    let (_, sale) = state.sales.get(0).expect("Can't be None");
+    assert_eq!(sale.token_owner, USERS[3].into());
+}
+
+#[test]
+fn vault_failures() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 1_000);
+    mint_approved_token(&sys, address_nft, USERS[1], 0);
+
+    // The code of a collection can't be initialized as a vault
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+    let res = marketplace.send(
+        ADMINS[0],
+        NftMarketplaceAction::SetVaultCode {
+            code_id: nft_collection_code_id.into_bytes().into(),
+        },
+    );
+    assert!(!res.main_failed());
+
+    let res = marketplace.send(
+        USERS[1],
+        NftMarketplaceAction::CreateVault {
+            collection_address: address_nft,
+            token_id: 0,
+            total_shares: 100,
+            reserve_price: 100_000_000_000_000,
+            buyout_duration_ms: 60_000,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The vault program was not initialized".to_string()
+    ));
+
+    // The token is returned to the owner
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert!(state.vaults.is_empty());
+    assert!(state.unreleased_tokens.is_empty());
+
+    let address_nft: [u8; 32] = address_nft.into();
+    let StateReplyNft::All(state) = sys
+        .get_program(address_nft)
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    let (_, token) = state
+        .tokens
+        .iter()
+        .find(|(token_id, _)| *token_id == 0)
+        .expect("Can't be None");
+    assert_eq!(token.owner, USERS[1].into());
+}
+
+#[test]
+fn raffle_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_000, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint and approve of two NFTs in the new collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for token_id in 0..2 {
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+
+    // Successful raffle creation
+    let ticket_price = 20_000_000_000_000;
+    let duration_ms = 30_000;
+    let duration_blocks = duration_ms / 3000 + 1;
+    let ends_at = sys.block_timestamp() + duration_ms;
+    let res = create_raffle(
+        &marketplace,
+        USERS[1],
+        address_nft,
+        0,
+        ticket_price,
+        3,
+        2,
+        ends_at,
+    );
+    assert!(!res.main_failed());
+
+    // Only the delayed message or an admin can draw the raffle
+    let res = marketplace.send(
+        USERS[2],
+        NftMarketplaceAction::DrawRaffle {
+            collection_address: address_nft,
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only program or admin can send this message.".to_string()
+    ));
+
+    // The raffle can't be drawn before it ends
+    let res = marketplace.send(
+        ADMINS[0],
+        NftMarketplaceAction::DrawRaffle {
+            collection_address: address_nft,
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This raffle does not exist or has not ended yet".to_string()
+    ));
+
+    // Successful purchase of all the tickets
+    sys.mint_to(USERS[2], 2 * ticket_price);
+    let res = buy_tickets(&marketplace, USERS[2], address_nft, 0, 2, 2 * ticket_price);
+    assert!(!res.main_failed());
+    let res = buy_tickets(&marketplace, USERS[3], address_nft, 0, 2, 0);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The number of tickets must be from 1 to 1".to_string()
+    ));
+    sys.mint_to(USERS[3], ticket_price);
+    let res = buy_tickets(&marketplace, USERS[3], address_nft, 0, 1, ticket_price);
+    assert!(!res.main_failed());
+
+    // The sold out raffle is drawn by the delayed message when it ends
+    sys.spend_blocks(duration_blocks as u32);
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let token = state
+            .tokens
+            .iter()
+            .find(|(token_id, _)| *token_id == 0)
+            .expect("Can't be None");
+        assert!(token.1.owner == USERS[2].into() || token.1.owner == USERS[3].into());
+    }
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    // the royalty is less than the existential deposit, so the owner receives the whole value
+    assert_eq!(balance, 3 * ticket_price, "Wrong balance");
+
+    // The raffle with too few tickets sold refunds the buyers
+    let ends_at = sys.block_timestamp() + duration_ms;
+    let res = create_raffle(
+        &marketplace,
+        USERS[1],
+        address_nft,
+        1,
+        ticket_price,
+        3,
+        2,
+        ends_at,
+    );
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[3], ticket_price);
+    let res = buy_tickets(&marketplace, USERS[3], address_nft, 1, 1, ticket_price);
+    assert!(!res.main_failed());
+
+    // the delayed message draws the raffle
+    sys.spend_blocks(duration_blocks as u32);
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.raffles.is_empty());
+    }
+    sys.claim_value_from_mailbox(USERS[3]);
+    let balance = sys.balance_of(USERS[3]);
+    assert_eq!(balance, ticket_price, "Wrong balance");
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let token = state
+            .tokens
+            .iter()
+            .find(|(token_id, _)| *token_id == 1)
+            .expect("Can't be None");
+        assert_eq!(token.1.owner, USERS[1].into())
+    }
+}
+
+#[test]
+fn raffle_failures() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 1_000);
+    mint_approved_token(&sys, address_nft, USERS[1], 0);
+
+    let ticket_price = 20_000_000_000_000;
+    let duration_ms = 30_000;
+    let ends_at = sys.block_timestamp() + duration_ms;
+
+    // Too many tickets
+    let res = create_raffle(
+        &marketplace,
+        USERS[1],
+        address_nft,
+        0,
+        ticket_price,
+        10_001,
+        2,
+        ends_at,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The maximum number of tickets must be from 1 to 10000 and not less than the minimum"
+            .to_string()
+    ));
+
+    let res = create_raffle(
+        &marketplace,
+        USERS[1],
+        address_nft,
+        0,
+        ticket_price,
+        3,
+        2,
+        ends_at,
+    );
+    assert!(!res.main_failed());
+
+    // Wrong payment
+    sys.mint_to(USERS[2], 3 * ticket_price);
+    let res = buy_tickets(&marketplace, USERS[2], address_nft, 0, 2, 3 * ticket_price);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The attached value must be equal to the price of the tickets".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), 3 * ticket_price, "Wrong balance");
+
+    // Wrong number of tickets
+    let res = buy_tickets(&marketplace, USERS[2], address_nft, 0, 4, 3 * ticket_price);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The number of tickets must be from 1 to 3".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), 3 * ticket_price, "Wrong balance");
+
+    // The raffle has ended, but hasn't been drawn yet
+    sys.spend_blocks((duration_ms / 3000) as u32);
+    let res = buy_tickets(&marketplace, USERS[2], address_nft, 0, 1, ticket_price);
+    assert!(!res.main_failed());
+    assert!(check_payload(0, &res, "The raffle has ended".to_string()));
+    assert_eq!(sys.balance_of(USERS[2]), 3 * ticket_price, "Wrong balance");
+
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    let (_, raffle) = state.raffles.get(0).expect("Can't be None");
+    assert!(raffle.tickets.is_empty());
+}
+
+#[test]
+fn batch_buy_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_000, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint, approve and sale of three NFTs with different prices
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let prices = [20_000_000_000_000, 30_000_000_000_000, 40_000_000_000_000];
+    for (token_id, price) in prices.iter().enumerate() {
+        let token_id = token_id as u64;
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+        let res = sale(&marketplace, USERS[1], address_nft, token_id, *price);
+        assert!(!res.main_failed());
+    }
+
+    // The listed token is bought, the missing one is skipped and the remainder is refunded
+    let value = 50_000_000_000_000;
+    sys.mint_to(USERS[2], value);
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuyMany {
+            items: vec![(address_nft, 0), (address_nft, 5)],
+            max_total: value,
+        },
+        value,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This sale does not exist".to_string()
+    ));
+    sys.claim_value_from_mailbox(USERS[2]);
+    let balance = sys.balance_of(USERS[2]);
+    assert_eq!(balance, value - prices[0], "Wrong balance");
+
+    // Nothing to buy, the value is returned
+    let value = 100_000_000_000_000;
+    sys.mint_to(USERS[3], value);
+    let res = marketplace.send_with_value(
+        USERS[3],
+        NftMarketplaceAction::BuyCheapest {
+            collection_address: address_nft,
+            count: 0,
+            max_total: value,
+        },
+        value,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The number of tokens to buy must be greater than zero".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[3]), value, "Wrong balance");
+
+    // The cheapest tokens are bought until the maximum total is reached
+    let res = marketplace.send_with_value(
+        USERS[3],
+        NftMarketplaceAction::BuyCheapest {
+            collection_address: address_nft,
+            count: 2,
+            max_total: 60_000_000_000_000,
+        },
+        value,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The price of the token exceeds the remaining budget".to_string()
+    ));
+    sys.claim_value_from_mailbox(USERS[3]);
+    let balance = sys.balance_of(USERS[3]);
+    assert_eq!(balance, value - prices[1], "Wrong balance");
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(state.sales.len(), 1);
+        assert_eq!(state.sales[0].0, (address_nft, 2));
+    }
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        for (token_id, owner) in [(0, USERS[2]), (1, USERS[3])] {
+            let token = state
+                .tokens
+                .iter()
+                .find(|(id, _)| *id == token_id)
+                .expect("Can't be None");
+            assert_eq!(token.1.owner, owner.into());
+        }
+    }
+}
+
+#[test]
+fn gift_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_000, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint and approve of three NFTs in the new collection
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for token_id in 0..3 {
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+    let price = 150_000_000_000_000;
+
+    // The token on sale is bought as a gift
+    let res = sale(&marketplace, USERS[1], address_nft, 0, price);
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[2], price);
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuyNft {
+            collection_address: address_nft,
+            token_id: 0,
+            recipient: Some(USERS[3].into()),
+            referrer: None,
+        },
+        price,
+    );
+    assert!(!res.main_failed());
+
+    // The offer is accepted in favor of the recipient
+    sys.mint_to(USERS[2], price);
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::CreateOffer {
+            collection_address: address_nft,
+            token_id: 1,
+            expires_at: None,
+            recipient: Some(USERS[3].into()),
+            referrer: None,
+        },
+        price,
+    );
+    assert!(!res.main_failed());
+    let offer = Offer {
+        collection_address: address_nft,
+        token_id: 1,
+        creator: USERS[2].into(),
+    };
+    let res = marketplace.send(USERS[1], NftMarketplaceAction::AcceptOffer { offer });
+    assert!(!res.main_failed());
+
+    // The winning bid delivers the token to the recipient
+    let duration_ms = 30_000;
+    let duration_blocks = duration_ms / 3000 + 1;
+    let res = create_auction(&marketplace, USERS[1], address_nft, 2, price, duration_ms);
+    assert!(!res.main_failed());
+    let bid = 200_000_000_000_000;
+    sys.mint_to(USERS[2], bid);
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::AddBid {
+            collection_address: address_nft,
+            token_id: 2,
+            recipient: Some(USERS[3].into()),
+            referrer: None,
+        },
+        bid,
+    );
+    assert!(!res.main_failed());
+    sys.spend_blocks(duration_blocks);
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        for token_id in 0..3 {
+            let token = state
+                .tokens
+                .iter()
+                .find(|(id, _)| *id == token_id)
+                .expect("Can't be None");
+            assert_eq!(token.1.owner, USERS[3].into());
+        }
+    }
+}
+
+#[test]
+fn referral_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_000, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint, approve and sale of two NFTs
+    let price = 200_000_000_000_000;
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for token_id in 0..2 {
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+        let res = sale(&marketplace, USERS[1], address_nft, token_id, price);
+        assert!(!res.main_failed());
+    }
+
+    // The seller hasn't agreed to pay a referral fee for the listing
+    sys.mint_to(USERS[2], price);
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuyNft {
+            collection_address: address_nft,
+            token_id: 0,
+            recipient: None,
+            referrer: Some(USERS[3].into()),
+        },
+        price,
+    );
+    assert!(!res.main_failed());
+    sys.claim_value_from_mailbox(USERS[3]);
+    assert_eq!(sys.balance_of(USERS[3]), 0, "Wrong balance");
+
+    // Only the seller can set the referral rate of the listing
+    let res = marketplace.send(
+        USERS[2],
+        NftMarketplaceAction::SetReferralRate {
+            collection_address: address_nft,
+            token_id: 1,
+            referral_rate: Some(2_000),
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only the seller can set the referral rate".to_string()
+    ));
+    let res = marketplace.send(
+        USERS[1],
+        NftMarketplaceAction::SetReferralRate {
+            collection_address: address_nft,
+            token_id: 1,
+            referral_rate: Some(2_000),
+        },
+    );
+    assert!(!res.main_failed());
+
+    // The buyer and the seller can't be the referrer, the value is returned
+    sys.mint_to(USERS[2], price);
+    for referrer in [USERS[2], USERS[1]] {
+        let res = marketplace.send_with_value(
+            USERS[2],
+            NftMarketplaceAction::BuyNft {
+                collection_address: address_nft,
+                token_id: 1,
+                recipient: None,
+                referrer: Some(referrer.into()),
+            },
+            price,
+        );
+        assert!(!res.main_failed());
+        assert!(check_payload(
+            0,
+            &res,
+            "The referrer can't be the buyer, the recipient or the seller".to_string()
+        ));
+        assert_eq!(sys.balance_of(USERS[2]), price, "Wrong balance");
+    }
+
+    // The referrer receives the rate set by the seller
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuyNft {
+            collection_address: address_nft,
+            token_id: 1,
+            recipient: None,
+            referrer: Some(USERS[3].into()),
+        },
+        price,
+    );
+    assert!(!res.main_failed());
+    sys.claim_value_from_mailbox(USERS[3]);
+    let balance = sys.balance_of(USERS[3]);
+    assert_eq!(balance, price / 5, "Wrong balance");
+
+    // the royalty is taken from the whole price, the referral fee only from the seller's part
+    sys.claim_value_from_mailbox(USERS[0]);
+    let balance = sys.balance_of(USERS[0]);
+    assert_eq!(balance, 2 * (price / 10), "Wrong balance");
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        price - price / 10 + price - price / 10 - price / 5,
+        "Wrong balance"
+    );
+}
+
+#[test]
+fn signed_order_success() {
+    use schnorrkel::{ExpansionMode, MiniSecretKey};
+
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_000, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // The account of the seller is the public key of the sr25519 key pair
+    let keypair = MiniSecretKey::from_bytes(&[7; 32])
+        .expect("Unable to create the secret key")
+        .expand_to_keypair(ExpansionMode::Ed25519);
+    let seller = keypair.public.to_bytes();
+
+    // Successful mint and approve NFT in the new collection
+    let res = nft_collection.send(seller, nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        seller,
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    // The seller signs the order off-chain
+    let price = 150_000_000_000_000;
+    let order = SignedOrder {
+        seller: seller.into(),
+        collection_address: address_nft,
+        token_id: 0,
+        price,
+        expires_at: sys.block_timestamp() + 60_000,
+        nonce: 0,
+    };
+    let sign = |order: &SignedOrder| {
+        let message = (ActorId::from(addres_marketplace), order).encode();
+        keypair
+            .secret
+            .sign_simple(b"substrate", &message, &keypair.public)
+            .to_bytes()
+    };
+    let signature = sign(&order);
+
+    // The order with a changed price is rejected
+    let mut changed_order = order.clone();
+    changed_order.price = price / 2;
+    sys.mint_to(USERS[3], price / 2);
+    let res = marketplace.send_with_value(
+        USERS[3],
+        NftMarketplaceAction::BuySignedOrder {
+            order: changed_order,
+            signature,
+        },
+        price / 2,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Invalid signature of the order".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[3]), price / 2, "Wrong balance");
+
+    // Successful purchase
+    sys.mint_to(USERS[2], price);
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuySignedOrder {
+            order: order.clone(),
+            signature,
+        },
+        price,
+    );
+    assert!(!res.main_failed());
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let token = state.tokens.get(0).expect("Can't be None");
+        assert_eq!(token.1.owner, USERS[2].into())
+    }
+    sys.claim_value_from_mailbox(seller);
+    let balance = sys.balance_of(seller);
+    assert_eq!(balance, price - price / 10, "Wrong balance");
+
+    // The order can't be filled twice
+    sys.mint_to(USERS[3], price);
+    let res = marketplace.send_with_value(
+        USERS[3],
+        NftMarketplaceAction::BuySignedOrder { order, signature },
+        price,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This order has already been filled or canceled".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[3]), price / 2 + price, "Wrong balance");
+
+    // The seller cancels the order by its nonce
+    let res = marketplace.send(seller, NftMarketplaceAction::CancelOrderNonce { nonce: 1 });
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.used_order_nonces.contains(&(seller.into(), 0)));
+        assert!(state.used_order_nonces.contains(&(seller.into(), 1)));
+    }
+    let res = marketplace.send(seller, NftMarketplaceAction::CancelOrderNonce { nonce: 1 });
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This nonce has already been used or canceled".to_string()
+    ));
+}
+
+#[test]
+fn signed_order_failures() {
+    use schnorrkel::{ExpansionMode, MiniSecretKey};
+
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 1_000);
+
+    let keypair = MiniSecretKey::from_bytes(&[7; 32])
+        .expect("Unable to create the secret key")
+        .expand_to_keypair(ExpansionMode::Ed25519);
+    let seller = keypair.public.to_bytes();
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+    let res = nft_collection.send(seller, nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        seller,
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+
+    let sign = |order: &SignedOrder| {
+        let message = (ActorId::from(addres_marketplace), order).encode();
+        keypair
+            .secret
+            .sign_simple(b"substrate", &message, &keypair.public)
+            .to_bytes()
+    };
+    let price = 150_000_000_000_000;
+    let order = SignedOrder {
+        seller: seller.into(),
+        collection_address: address_nft,
+        token_id: 0,
+        price,
+        expires_at: sys.block_timestamp() + 30_000,
+        nonce: 0,
+    };
+    sys.mint_to(USERS[2], 2 * price);
+
+    // The price is less than the existential deposit
+    let mut cheap_order = order.clone();
+    cheap_order.price = 1_000;
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuySignedOrder {
+            signature: sign(&cheap_order),
+            order: cheap_order,
+        },
+        1_000,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The price must be greater than existential deposit".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), 2 * price, "Wrong balance");
+
+    // Wrong payment
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuySignedOrder {
+            signature: sign(&order),
+            order: order.clone(),
+        },
+        2 * price,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The attached value must be equal to the price of the order".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), 2 * price, "Wrong balance");
+
+    // The seller revoked the approval, so the nonce stays unused
+    let res = nft_collection.send(seller, nft_io::NftAction::RevokeApproval { token_id: 0 });
+    assert!(!res.main_failed());
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuySignedOrder {
+            signature: sign(&order),
+            order: order.clone(),
+        },
+        price,
+    );
+    assert!(!res.main_failed());
+    assert_eq!(sys.balance_of(USERS[2]), 2 * price, "Wrong balance");
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert!(state.used_order_nonces.is_empty());
+
+    // The order has expired
+    sys.spend_blocks(10);
+    let res = marketplace.send_with_value(
+        USERS[2],
+        NftMarketplaceAction::BuySignedOrder {
+            signature: sign(&order),
+            order,
+        },
+        price,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(0, &res, "This order has expired".to_string()));
+    assert_eq!(sys.balance_of(USERS[2]), 2 * price, "Wrong balance");
+}
+
+#[test]
+fn order_book_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let royalty = 1_000;
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), royalty, Some(4), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint and approve of four NFTs, the first three are listed
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let prices = [
+        110_000_000_000_000,
+        120_000_000_000_000,
+        200_000_000_000_000,
+    ];
+    for token_id in 0..4 {
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+    for (token_id, price) in prices.iter().enumerate() {
+        let res = sale(&marketplace, USERS[1], address_nft, token_id as u64, *price);
+        assert!(!res.main_failed());
+    }
+
+    let state_reply = marketplace
+        .read_state(StateQuery::OrderBookDepth {
+            collection_address: address_nft,
+            depth: 2,
+        })
+        .expect("Unexpected invalid state.");
+    if let StateReply::OrderBookDepth { asks, bids } = state_reply {
+        assert_eq!(asks, vec![(prices[0], 1), (prices[1], 1)]);
+        assert!(bids.is_empty());
+    }
+
+    // The new bid crosses the two lowest asks, they are executed at the ask price
+    let offer_price = 150_000_000_000_000;
+    let quantity = 3;
+    let value = offer_price * quantity as u128;
+    sys.mint_to(USERS[2], value);
+    let res = create_collection_offer(&marketplace, USERS[2], address_nft, quantity, value);
+    assert!(!res.main_failed());
+    let trades = vec![
+        Trade {
+            token_id: 0,
+            seller: USERS[1].into(),
+            buyer: USERS[2].into(),
+            price: prices[0],
+        },
+        Trade {
+            token_id: 1,
+            seller: USERS[1].into(),
+            buyer: USERS[2].into(),
+            price: prices[1],
+        },
+    ];
+    assert!(res.contains(&(
+        USERS[2],
+        Ok::<NftMarketplaceEvent, NftMarketplaceError>(NftMarketplaceEvent::OrdersMatched {
+            collection_address: address_nft,
+            trades,
+            remaining_offer: Some(CollectionOfferInfo {
+                price: offer_price,
+                quantity: 1,
+            }),
+        })
+        .encode()
+    )));
+    let state_reply = marketplace
+        .read_state(StateQuery::BestBidAsk(address_nft))
+        .expect("Unexpected invalid state.");
+    if let StateReply::BestBidAsk { best_bid, best_ask } = state_reply {
+        assert_eq!(best_bid, Some(offer_price));
+        assert_eq!(best_ask, Some(prices[2]));
+    }
+
+    // The new ask crosses the highest bid, it is executed at the bid price
+    let res = sale(&marketplace, USERS[1], address_nft, 3, 140_000_000_000_000);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.collection_offers.is_empty());
+        assert_eq!(state.sales.len(), 1);
+        assert_eq!(state.sales[0].0, (address_nft, 2));
+    }
+
+    // the difference between the bid and the ask prices is returned to the bidder
+    sys.claim_value_from_mailbox(USERS[2]);
+    let balance = sys.balance_of(USERS[2]);
+    assert_eq!(
+        balance,
+        2 * offer_price - prices[0] - prices[1],
+        "Wrong balance"
+    );
+
+    let sold = prices[0] + prices[1] + offer_price;
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        sold - sold * royalty as u128 / 10_000,
+        "Wrong balance"
+    );
+
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let owners: [(u64, ActorId); 4] = [
+            (0, USERS[2].into()),
+            (1, USERS[2].into()),
+            (2, addres_marketplace.into()),
+            (3, USERS[2].into()),
+        ];
+        for (token_id, owner) in owners {
+            let token = state
+                .tokens
+                .iter()
+                .find(|(id, _)| *id == token_id)
+                .expect("Can't be None");
+            assert_eq!(token.1.owner, owner);
+        }
+    }
+}
+
+#[test]
+fn order_book_failures() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 1_000);
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // The cheapest ask is listed without custody, the next one is kept by the marketplace
+    let prices = [110_000_000_000_000, 120_000_000_000_000];
+    for token_id in 0..2 {
+        mint_approved_token(&sys, address_nft, USERS[1], token_id);
+    }
+    let res = marketplace.send(
+        USERS[1],
+        NftMarketplaceAction::SaleNftNonCustodial {
+            collection_address: address_nft,
+            token_id: 0,
+            price: prices[0],
+        },
+    );
+    assert!(!res.main_failed());
+    let res = sale(&marketplace, USERS[1], address_nft, 1, prices[1]);
+    assert!(!res.main_failed());
+
+    // The seller revokes the approval, so the cheapest ask can't be executed
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::RevokeApproval { token_id: 0 });
+    assert!(!res.main_failed());
+
+    // The failed ask is skipped and the bid is filled with the next one
+    let offer_price = 150_000_000_000_000;
+    sys.mint_to(USERS[2], offer_price);
+    let res = create_collection_offer(&marketplace, USERS[2], address_nft, 1, offer_price);
+    assert!(!res.main_failed());
+    assert!(res.contains(&(
+        USERS[2],
+        Ok::<NftMarketplaceEvent, NftMarketplaceError>(NftMarketplaceEvent::OrdersMatched {
+            collection_address: address_nft,
+            trades: vec![Trade {
+                token_id: 1,
+                seller: USERS[1].into(),
+                buyer: USERS[2].into(),
+                price: prices[1],
+            }],
+            remaining_offer: None,
+        })
+        .encode()
+    )));
+
+    // The invalid listing is removed from the book
+    let StateReply::OrderBookDepth { asks, bids } = marketplace
+        .read_state(StateQuery::OrderBookDepth {
+            collection_address: address_nft,
+            depth: 2,
+        })
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert!(asks.is_empty());
+    assert!(bids.is_empty());
+    assert_eq!(
+        sys.balance_of(USERS[2]),
+        offer_price - prices[1],
+        "Wrong balance"
+    );
+}
+
+#[test]
+fn royalty_split_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection, the royalty of 15% is split between the owner and USERS[3]
+    let mut init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_500, Some(3), 0);
+    init_nft_payload.config.royalty_recipients =
+        vec![(USERS[0].into(), 1_000), (USERS[3].into(), 500)];
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint, approve and sale of two NFTs
+    let prices = [300_000_000_000_000, 150_000_000_000_000];
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for (token_id, price) in prices.iter().enumerate() {
+        let token_id = token_id as u64;
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+        let res = sale(&marketplace, USERS[1], address_nft, token_id, *price);
+        assert!(!res.main_failed());
+    }
+
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(
+            state.sales[0].1.royalty_recipients,
+            vec![(USERS[0].into(), 1_000), (USERS[3].into(), 500)]
+        );
+    }
+
+    // Each recipient receives its share of the price
+    sys.mint_to(USERS[2], prices[0]);
+    let res = buy(&marketplace, USERS[2], address_nft, 0, prices[0]);
+    assert!(!res.main_failed());
+    sys.claim_value_from_mailbox(USERS[0]);
+    sys.claim_value_from_mailbox(USERS[1]);
+    sys.claim_value_from_mailbox(USERS[3]);
+    assert_eq!(sys.balance_of(USERS[0]), prices[0] / 10, "Wrong balance");
+    assert_eq!(sys.balance_of(USERS[3]), prices[0] / 20, "Wrong balance");
+    let seller_balance = prices[0] - prices[0] * 1_500 / 10_000;
+    assert_eq!(sys.balance_of(USERS[1]), seller_balance, "Wrong balance");
+
+    // The share that doesn't exceed the minimum transfer value stays with the seller
+    sys.mint_to(USERS[2], prices[1]);
+    let res = buy(&marketplace, USERS[2], address_nft, 1, prices[1]);
+    assert!(!res.main_failed());
+    sys.claim_value_from_mailbox(USERS[1]);
+    sys.claim_value_from_mailbox(USERS[3]);
+    assert_eq!(sys.balance_of(USERS[3]), prices[0] / 20, "Wrong balance");
+    assert_eq!(
+        sys.balance_of(USERS[1]),
+        seller_balance + prices[1] - prices[1] / 10,
+        "Wrong balance"
+    );
+}
+
+#[test]
+fn token_royalty_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let royalty = 1_000;
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), royalty, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for token_id in 0..2 {
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+
+    // Only the collection owner can override the royalty of the token
+    let token_royalty = 500;
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::SetTokenRoyalty {
+            token_id: 1,
+            royalty: Some(token_royalty),
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only collection owner can send this message".to_string()
+    ));
+
+    // The royalty of the token held by another account can't be raised
+    let res = nft_collection.send(
+        USERS[0],
+        nft_io::NftAction::SetTokenRoyalty {
+            token_id: 1,
+            royalty: Some(2_500),
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The royalty of the token held by another account can only be lowered".to_string()
+    ));
+
+    let res = nft_collection.send(
+        USERS[0],
+        nft_io::NftAction::SetTokenRoyalty {
+            token_id: 1,
+            royalty: Some(token_royalty),
+        },
+    );
+    assert!(!res.main_failed());
+
+    // The marketplace takes the effective royalty of each token
+    let price = 200_000_000_000_000;
+    for token_id in 0..2 {
+        let res = sale(&marketplace, USERS[1], address_nft, token_id, price);
+        assert!(!res.main_failed());
+    }
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        for (token_id, royalty) in [(0, royalty), (1, token_royalty)] {
+            let (_, nft) = state
+                .sales
+                .iter()
+                .find(|((_, id), _)| *id == token_id)
+                .expect("Can't be None");
+            assert_eq!(nft.royalty_recipients, vec![(USERS[0].into(), royalty)]);
+        }
+    }
+
+    sys.mint_to(USERS[2], price);
+    let res = buy(&marketplace, USERS[2], address_nft, 1, price);
+    assert!(!res.main_failed());
+    let percent_to_collection_owner = price * token_royalty as u128 / 10_000;
+    sys.claim_value_from_mailbox(USERS[0]);
+    let balance = sys.balance_of(USERS[0]);
+    assert_eq!(balance, percent_to_collection_owner, "Wrong balance");
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        price - percent_to_collection_owner,
+        "Wrong balance"
+    );
+}
+
+#[test]
+fn non_custodial_sale_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Сreating a new type of collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // Create collection
+    let royalty = 1_000;
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), royalty, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+
+    // Successful mint, approve and non-custodial sale of two NFTs
+    let price = 200_000_000_000_000;
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    for token_id in 0..2 {
+        let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+        assert!(!res.main_failed());
+        let res = nft_collection.send(
+            USERS[1],
+            nft_io::NftAction::Approve {
+                to: addres_marketplace.into(),
+                token_id,
+            },
+        );
+        assert!(!res.main_failed());
+        let res = marketplace.send(
+            USERS[1],
+            NftMarketplaceAction::SaleNftNonCustodial {
+                collection_address: address_nft,
+                token_id,
+                price,
+            },
+        );
+        assert!(!res.main_failed());
+    }
+
+    // The listed tokens stay with the seller
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        for (_, token) in state.tokens.iter() {
+            assert_eq!(token.owner, USERS[1].into());
+        }
+    }
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(state.sales.len(), 2);
+        assert!(state.sales.iter().all(|(_, nft)| !nft.custodial));
+    }
+
+    // The listing is valid while the seller owns and approves the token
+    let res = marketplace.send(
+        USERS[2],
+        NftMarketplaceAction::InvalidateListing {
+            collection_address: address_nft,
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The seller still owns and approves the token".to_string()
+    ));
+
+    // The token is transferred directly from the seller to the buyer
+    sys.mint_to(USERS[2], price);
+    let res = buy(&marketplace, USERS[2], address_nft, 0, price);
+    assert!(!res.main_failed());
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let (_, token) = state
+            .tokens
+            .iter()
+            .find(|(token_id, _)| *token_id == 0)
+            .expect("Can't be None");
+        assert_eq!(token.owner, USERS[2].into());
+    }
+    let percent_to_collection_owner = price * royalty as u128 / 10_000;
+    sys.claim_value_from_mailbox(USERS[0]);
+    let balance = sys.balance_of(USERS[0]);
+    assert_eq!(balance, percent_to_collection_owner, "Wrong balance");
+    sys.claim_value_from_mailbox(USERS[1]);
+    let balance = sys.balance_of(USERS[1]);
+    assert_eq!(
+        balance,
+        price - percent_to_collection_owner,
+        "Wrong balance"
+    );
+
+    // The listing isn't removed if the collection fails to return the token info
+    let gas_for_get_token_info = |gas| {
+        update_config(
+            &marketplace,
+            ADMINS[0],
+            None,
+            None,
+            None,
+            None,
+            Some(gas),
+            None,
+            None,
+            None,
+            None,
+        )
+    };
+    let res = gas_for_get_token_info(1_000_000);
+    assert!(!res.main_failed());
+    let res = marketplace.send(
+        USERS[3],
+        NftMarketplaceAction::InvalidateListing {
+            collection_address: address_nft,
+            token_id: 1,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The collection failed to return the token info".to_string()
+    ));
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert_eq!(state.sales.len(), 1);
+    let res = gas_for_get_token_info(5_000_000_000);
+    assert!(!res.main_failed());
+
+    // The listing is kept if the collection fails to transfer the token
+    let res = update_config(
+        &marketplace,
+        ADMINS[0],
+        None,
+        Some(1_000_000),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[2], price);
+    let res = buy(&marketplace, USERS[2], address_nft, 1, price);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "The collection failed to transfer the token".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), price, "Wrong balance");
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert_eq!(state.sales.len(), 1);
+    let res = update_config(
+        &marketplace,
+        ADMINS[0],
+        None,
+        Some(5_000_000_000),
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(!res.main_failed());
+
+    // Anyone can remove the listing after the seller revokes the approval
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::RevokeApproval { token_id: 1 });
+    assert!(!res.main_failed());
+    let res = marketplace.send(
+        USERS[3],
+        NftMarketplaceAction::InvalidateListing {
+            collection_address: address_nft,
+            token_id: 1,
+        },
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert!(state.sales.is_empty());
+    }
+}
+
+#[test]
+fn register_external_collection_success() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // The collection is deployed outside the marketplace
+    let nft_collection =
+        gtest::Program::from_file(&sys, "target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 0, Some(3), 0);
+    let res = nft_collection.send(USERS[0], init_nft_payload);
+    assert!(!res.main_failed());
+    let address_nft: [u8; 32] = nft_collection.id().into();
+    let address_nft: ActorId = address_nft.into();
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+
+    // The token must exist to name the collection owner
+    let res = register_external_collection(
+        &marketplace,
+        USERS[0],
+        address_nft,
+        name_simple_nft.clone(),
+        1,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "NonFungibleToken: token does not exist".to_string()
+    ));
+
+    // Only the collection owner or an admin can register the collection
+    let res = register_external_collection(
+        &marketplace,
+        USERS[1],
+        address_nft,
+        name_simple_nft.clone(),
+        0,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only the owner of the collection can register it".to_string()
+    ));
+
+    // The admin registers the collection on behalf of its owner
+    let res = register_external_collection(
+        &marketplace,
+        ADMINS[0],
+        address_nft,
+        name_simple_nft.clone(),
+        0,
+    );
+    assert!(!res.main_failed());
+    assert!(res.contains(&(
+        ADMINS[0],
+        Ok::<NftMarketplaceEvent, NftMarketplaceError>(
+            NftMarketplaceEvent::ExternalCollectionRegistered {
+                type_name: name_simple_nft.clone(),
+                collection_address: address_nft,
+                owner: USERS[0].into(),
+            }
+        )
+        .encode()
+    )));
+
+    let res = register_external_collection(
+        &marketplace,
+        USERS[0],
+        address_nft,
+        name_simple_nft.clone(),
+        0,
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This collection is already in the marketplace".to_string()
+    ));
+
+    let state_reply = marketplace
+        .read_state(StateQuery::GetCollectionInfo(address_nft))
+        .expect("Unexpected invalid state.");
+    if let StateReply::CollectionInfo(Some(info)) = state_reply {
+        assert_eq!(info.owner, USERS[0].into());
+        assert_eq!(info.origin, CollectionOrigin::Imported);
+        assert_eq!(info.capabilities.version, PROTOCOL_VERSION);
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+    }
+
+    // The tokens of the imported collection can be traded
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    let price = 150_000_000_000_000;
+    let res = sale(&marketplace, USERS[1], address_nft, 0, price);
+    assert!(!res.main_failed());
+    sys.mint_to(USERS[2], price);
+    let res = buy(&marketplace, USERS[2], address_nft, 0, price);
+    assert!(!res.main_failed());
+    let state_reply = nft_collection
+        .read_state(StateQueryNft::All)
+        .expect("Unexpected invalid state.");
+    if let StateReplyNft::All(state) = state_reply {
+        let (_, token) = state
+            .tokens
+            .iter()
+            .find(|(token_id, _)| *token_id == 0)
+            .expect("Can't be None");
+        assert_eq!(token.owner, USERS[2].into());
+    }
+}
+
+#[test]
+fn collection_type_versions() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This collection type already exists".to_string()
+    ));
+
+    // The first collection is created with the first version of the type
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 0, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    // Only admin can update the type
+    let meta_link = "My Meta v2".to_string();
+    let update = NftMarketplaceAction::UpdateCollectionType {
+        type_name: name_simple_nft.clone(),
+        code_id: nft_collection_code_id.into_bytes().into(),
+        meta_link: meta_link.clone(),
+    };
+    let res = marketplace.send_bytes(USERS[0], update.encode());
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "Only admin can send this message".to_string()
+    ));
+    let res = marketplace.send_bytes(ADMINS[0], update.encode());
+    assert!(!res.main_failed());
+    assert!(res.contains(&(
+        ADMINS[0],
+        Ok::<NftMarketplaceEvent, NftMarketplaceError>(
+            NftMarketplaceEvent::CollectionTypeUpdated {
+                type_name: name_simple_nft.clone(),
+                code_id: nft_collection_code_id.into_bytes().into(),
+                meta_link: meta_link.clone(),
+                version: 2,
+            }
+        )
+        .encode()
+    )));
+
+    // The existing collection keeps the version it was created with
+    let state_reply = marketplace
+        .read_state(StateQuery::GetCollectionInfo(address_nft))
+        .expect("Unexpected invalid state.");
+    if let StateReply::CollectionInfo(Some(info)) = state_reply {
+        assert_eq!(info.type_version, 1);
+        assert_eq!(info.meta_link, "My Meta".to_string());
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+    }
+
+    // A new collection is created with the new version
+    let init_nft_payload = get_init_nft_payload(USERS[1].into(), 0, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[1],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft_new = if let StateReply::AllCollections(state) = state_reply {
+        state
+            .iter()
+            .find(|(address, _)| *address != address_nft)
+            .expect("Can't be None")
+            .0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+    let state_reply = marketplace
+        .read_state(StateQuery::GetCollectionInfo(address_nft_new))
+        .expect("Unexpected invalid state.");
+    if let StateReply::CollectionInfo(Some(info)) = state_reply {
+        assert_eq!(info.type_version, 2);
+        assert_eq!(info.meta_link, meta_link);
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+    }
+
+    // The type can't be removed while there are collections of it
+    let res = marketplace.send(
+        ADMINS[0],
+        NftMarketplaceAction::RemoveCollectionType {
+            type_name: name_simple_nft.clone(),
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "There are collections of this type in the marketplace".to_string()
+    ));
+
+    // New collections of a deprecated type can't be created
+    let res = marketplace.send(
+        ADMINS[0],
+        NftMarketplaceAction::DeprecateCollectionType {
+            type_name: name_simple_nft.clone(),
+        },
+    );
+    assert!(!res.main_failed());
+    let init_nft_payload = get_init_nft_payload(USERS[2].into(), 0, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[2],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This collection type is deprecated".to_string()
+    ));
+
+    // But the existing collections are still traded
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+    let res = nft_collection.send(USERS[1], nft_io::NftAction::Mint);
+    assert!(!res.main_failed());
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        nft_io::NftAction::Approve {
+            to: addres_marketplace.into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    let price = 150_000_000_000_000;
+    let res = sale(&marketplace, USERS[1], address_nft, 0, price);
+    assert!(!res.main_failed());
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(state.sales.len(), 1);
+    }
+}
+
+#[test]
+fn trait_offer_failures() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let address_nft = init_simple_collection(&sys, USERS[0], 1_000);
+
+    // The simple collection doesn't combine tokens from layers
+    let offer_price = 150_000_000_000_000;
+    sys.mint_to(USERS[2], offer_price);
+    let res = create_trait_offer(&marketplace, USERS[2], address_nft, 1, 5, offer_price);
+    assert!(!res.main_failed());
+    assert!(check_payload(
+        0,
+        &res,
+        "This collection does not support trait offers".to_string()
+    ));
+    assert_eq!(sys.balance_of(USERS[2]), offer_price, "Wrong balance");
+
+    let StateReply::All(state) = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    assert!(state.trait_offers.is_empty());
+}
@@ -79,6 +79,7 @@ fn successful_basics() {
             collection_tags: vec!["tag1".to_string()],
             additional_links,
             royalty: 0,
+            royalty_recipients: vec![],
             user_mint_limit: 3.into(),
             listening_capabilities: ListenCapability::Demo,
             payment_for_mint: 0,
@@ -128,6 +129,7 @@ fn successful_basics() {
         collection_tags: vec!["tag1".to_string()],
         additional_links: None,
         royalty: 0,
+        royalty_recipients: vec![],
         user_mint_limit: 3.into(),
         listening_capabilities: ListenCapability::Demo,
         payment_for_mint: 0,
@@ -328,6 +330,7 @@ fn failures() {
             collection_tags: vec!["tag1".to_string()],
             additional_links,
             royalty: 0,
+            royalty_recipients: vec![],
             user_mint_limit: 0.into(),
             listening_capabilities: ListenCapability::Demo,
             payment_for_mint: 0,
@@ -446,6 +449,7 @@ fn failures() {
         collection_tags: vec!["tag1".to_string()],
         additional_links: None,
         royalty: 0,
+        royalty_recipients: vec![],
         user_mint_limit: 3.into(),
         listening_capabilities: ListenCapability::Demo,
         payment_for_mint: 0,
@@ -602,6 +606,7 @@ fn check_auto_changing_rules() {
             collection_tags: vec!["tag1".to_string()],
             additional_links,
             royalty: 0,
+            royalty_recipients: vec![],
             user_mint_limit: 3.into(),
             listening_capabilities: ListenCapability::Demo,
             payment_for_mint: 0,
@@ -732,6 +737,7 @@ fn check_transferable() {
             collection_tags: vec!["tag1".to_string()],
             additional_links,
             royalty: 0,
+            royalty_recipients: vec![],
             user_mint_limit: 3.into(),
             listening_capabilities: ListenCapability::Demo,
             payment_for_mint: 0,
@@ -883,6 +889,7 @@ fn check_payment_for_mint() {
             collection_tags: vec!["tag1".to_string()],
             additional_links,
             royalty: 0,
+            royalty_recipients: vec![],
             user_mint_limit: 3.into(),
             listening_capabilities: ListenCapability::Demo,
             payment_for_mint: 0,
@@ -955,6 +962,121 @@ fn check_payment_for_mint() {
     ));
 }
 
+#[test]
+fn check_listen_capability() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/music_nft.opt.wasm");
+
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    let img_data = ImageData {
+        limit_copies: Some(1),
+        description: None,
+        auto_changing_rules: None,
+    };
+    let links_and_data: Vec<(Links, ImageData)> = (0..10)
+        .map(|i| {
+            (
+                Links {
+                    img_link: None,
+                    music_link: format!("Img-{}", i),
+                },
+                img_data.clone(),
+            )
+        })
+        .collect();
+    let init_nft_payload = MusicNftInit {
+        collection_owner: USERS[0].into(),
+        config: Config {
+            name: "User Collection".to_string(),
+            description: "User Collection".to_string(),
+            collection_banner: "Collection banner".to_string(),
+            collection_logo: "Collection logo".to_string(),
+            collection_tags: vec!["tag1".to_string()],
+            additional_links: None,
+            royalty: 0,
+            royalty_recipients: vec![],
+            user_mint_limit: 3.into(),
+            listening_capabilities: ListenCapability::Demo,
+            payment_for_mint: 0,
+            transferable: Some(0),
+            sellable: Some(0),
+        },
+        links_and_data,
+    };
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft,
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let StateReply::AllCollections(collections) = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    let address_nft: [u8; 32] = collections[0].0.into();
+    let nft_collection = sys.get_program(address_nft);
+
+    let res = nft_collection.send(USERS[1], MusicNftAction::Mint);
+    assert!(!res.main_failed());
+
+    // The owner lends the token for two blocks
+    let res = nft_collection.send(
+        USERS[1],
+        MusicNftAction::SetUser {
+            token_id: 0,
+            user: USERS[2].into(),
+            expires: sys.block_timestamp() + 6_000,
+        },
+    );
+    assert!(!res.main_failed());
+    assert!(matches!(
+        listen_capability(&nft_collection, USERS[2]),
+        ListenCapability::AvailableFull
+    ));
+    assert!(matches!(
+        listen_capability(&nft_collection, USERS[1]),
+        ListenCapability::Demo
+    ));
+
+    // The rights return to the owner when the rent is over
+    sys.spend_blocks(3);
+    assert!(matches!(
+        listen_capability(&nft_collection, USERS[1]),
+        ListenCapability::AvailableFull
+    ));
+    assert!(matches!(
+        listen_capability(&nft_collection, USERS[2]),
+        ListenCapability::Demo
+    ));
+}
+
+fn listen_capability(nft_collection: &Program, account: u64) -> ListenCapability {
+    let StateReplyNft::ListenCapability(capability) = nft_collection
+        .read_state(StateQueryNft::ListenCapability {
+            account: account.into(),
+            token_id: 0,
+        })
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    capability
+}
+
 fn get_state(nft_collection: &Program) -> Option<NftState> {
     let state_reply = nft_collection
         .read_state(StateQueryNft::All)
@@ -4,7 +4,7 @@ mod utils;
 use gtest::Program;
 use nft_io::{
     Action, AdditionalLinks, Config, ImageData, NftAction, NftError, NftEvent, NftInit, NftState,
-    StateQuery as StateQueryNft, StateReply as StateReplyNft,
+    RoyaltyInfo, StateQuery as StateQueryNft, StateReply as StateReplyNft,
 };
 use nft_marketplace_io::*;
 
@@ -84,6 +84,7 @@ fn successful_basics() {
         collection_tags: vec!["tag1".to_string()],
         additional_links: None,
         royalty: 0,
+        royalty_recipients: vec![],
         user_mint_limit: 3.into(),
         payment_for_mint: 0,
         transferable: Some(0),
@@ -328,6 +329,7 @@ fn failures() {
         collection_tags: vec!["tag1".to_string()],
         additional_links: None,
         royalty: 0,
+        royalty_recipients: vec![],
         user_mint_limit: 3.into(),
         payment_for_mint: 0,
         transferable: Some(0),
@@ -467,6 +469,7 @@ fn check_auto_changing_rules() {
             collection_tags: vec!["tag1".to_string()],
             additional_links,
             royalty: 0,
+            royalty_recipients: vec![],
             user_mint_limit: 3.into(),
             payment_for_mint: 0,
             transferable: Some(0),
@@ -731,6 +734,202 @@ fn check_payment_for_mint() {
     ));
 }
 
+#[test]
+fn check_royalty_info() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Successful addition of a new collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    // The royalty of 15% is split between the collection owner and USERS[3]
+    let mut init_nft_payload = get_init_nft_payload(USERS[0].into(), 1_500, Some(3), 0);
+    init_nft_payload.config.royalty_recipients =
+        vec![(USERS[0].into(), 1_000), (USERS[3].into(), 500)];
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft);
+
+    // The token does not exist yet
+    let sale_price = 1_000_000;
+    let res = nft_collection.send(
+        USERS[2],
+        NftAction::RoyaltyInfo {
+            token_id: 0,
+            sale_price,
+        },
+    );
+    assert!(!res.main_failed());
+    let message: Result<NftEvent, NftError> = Err(NftError(
+        "NonFungibleToken: token does not exist".to_owned(),
+    ));
+    assert!(res.contains(&(USERS[2], message.encode())));
+
+    let res = nft_collection.send(USERS[1], NftAction::Mint);
+    assert!(!res.main_failed());
+
+    // Any program can find out the royalty due on a sale
+    let res = nft_collection.send(
+        USERS[2],
+        NftAction::RoyaltyInfo {
+            token_id: 0,
+            sale_price,
+        },
+    );
+    assert!(!res.main_failed());
+    let message: Result<NftEvent, NftError> = Ok(NftEvent::RoyaltyInfoReceived(RoyaltyInfo {
+        receivers: vec![(USERS[0].into(), 100_000), (USERS[3].into(), 50_000)],
+        amount: 150_000,
+    }));
+    assert!(res.contains(&(USERS[2], message.encode())));
+}
+
+#[test]
+fn check_approve_for_all() {
+    let sys = utils::initialize_system();
+    init_marketplace(&sys);
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+
+    // Successful addition of a new collection
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    let init_nft_payload = get_init_nft_payload(USERS[0].into(), 0, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        USERS[0],
+        name_simple_nft.clone(),
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+
+    let state_reply = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.");
+    let address_nft = if let StateReply::AllCollections(state) = state_reply {
+        assert!(!state.is_empty(), "Collections shouldn't be empty");
+        state[0].0
+    } else {
+        assert!(false, "Unexpected StateReply variant");
+        0.into()
+    };
+
+    let address_nft_2: [u8; 32] = address_nft.into();
+    let nft_collection = sys.get_program(address_nft_2);
+    for _ in 0..3 {
+        let res = nft_collection.send(USERS[1], NftAction::Mint);
+        assert!(!res.main_failed());
+    }
+
+    // The operator can transfer any token of the owner
+    let res = nft_collection.send(
+        USERS[1],
+        NftAction::ApproveForAll {
+            operator: USERS[2].into(),
+            approved: true,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = nft_collection.send(
+        USERS[2],
+        NftAction::TransferFrom {
+            from: USERS[1].into(),
+            to: USERS[3].into(),
+            token_id: 0,
+        },
+    );
+    assert!(!res.main_failed());
+    let state = get_state(&nft_collection).expect("Unexpected invalid state.");
+    let (_, token) = state
+        .tokens
+        .iter()
+        .find(|(token_id, _)| *token_id == 0)
+        .expect("Can't be None");
+    assert_eq!(token.owner, USERS[3].into());
+
+    let res = nft_collection.send(
+        USERS[1],
+        NftAction::ApproveForAll {
+            operator: USERS[2].into(),
+            approved: false,
+        },
+    );
+    assert!(!res.main_failed());
+    let res = nft_collection.send(
+        USERS[2],
+        NftAction::IsApprovedForAll {
+            owner: USERS[1].into(),
+            operator: USERS[2].into(),
+        },
+    );
+    assert!(!res.main_failed());
+    let message: Result<NftEvent, NftError> = Ok(NftEvent::IsApprovedForAll(false));
+    assert!(res.contains(&(USERS[2], message.encode())));
+
+    // A single approval of the marketplace is enough to list all the tokens
+    let addres_marketplace: [u8; 32] = marketplace.id().into();
+    let res = nft_collection.send(
+        USERS[1],
+        NftAction::ApproveForAll {
+            operator: addres_marketplace.into(),
+            approved: true,
+        },
+    );
+    assert!(!res.main_failed());
+    let price = 100_000_000_000_000;
+    for token_id in 1..3 {
+        let res = sale(&marketplace, USERS[1], address_nft, token_id, price);
+        assert!(!res.main_failed());
+    }
+    let state_reply = marketplace
+        .read_state(StateQuery::All)
+        .expect("Unexpected invalid state.");
+    if let StateReply::All(state) = state_reply {
+        assert_eq!(state.sales.len(), 2);
+    }
+    let state = get_state(&nft_collection).expect("Unexpected invalid state.");
+    assert_eq!(
+        state.operator_approvals,
+        vec![(USERS[1].into(), vec![addres_marketplace.into()])]
+    );
+}
+
 fn get_state(nft_collection: &Program) -> Option<NftState> {
     let state_reply = nft_collection
         .read_state(StateQueryNft::All)
@@ -1,7 +1,10 @@
-use gstd::{ActorId, CodeId};
+use gstd::{prelude::Encode, ActorId, CodeId};
 use gtest::{Program, RunResult, System};
-use nft_io::{Config, ImageData, NftInit};
-use nft_marketplace_io::{NftMarketplaceAction, NftMarketplaceInit, Offer};
+use nft_io::{Config, ImageData, NftAction, NftInit};
+use nft_marketplace_io::{
+    CollectionOffer, NftMarketplaceAction, NftMarketplaceInit, Offer, StateQuery, StateReply,
+    TraitOffer,
+};
 
 mod common;
 pub mod prelude;
@@ -18,6 +21,7 @@ pub fn init_marketplace(sys: &System) {
         gas_for_close_auction: 10_000_000_000,
         gas_for_delete_collection: 5_000_000_000,
         gas_for_get_token_info: 5_000_000_000,
+        gas_for_cleanup_offers: 5_000_000_000,
         time_between_create_collections: 3_600_000, // 1 hour in milliseconds
         minimum_transfer_value: 10_000_000_000_000,
         ms_in_block: 3_000,
@@ -26,6 +30,54 @@ pub fn init_marketplace(sys: &System) {
     assert!(!res.main_failed());
 }
 
+/// Adds the simple nft type and creates a collection of the owner, returns the collection address
+pub fn init_simple_collection(sys: &System, owner: u64, royalty: u16) -> ActorId {
+    let marketplace = sys.get_program(1);
+    let nft_collection_code_id =
+        sys.submit_code("target/wasm32-unknown-unknown/debug/nft.opt.wasm");
+    let name_simple_nft = "Simple NFT".to_string();
+    let res = add_new_collection(
+        &marketplace,
+        ADMINS[0],
+        nft_collection_code_id.into_bytes().into(),
+        name_simple_nft.clone(),
+    );
+    assert!(!res.main_failed());
+
+    let init_nft_payload = get_init_nft_payload(owner.into(), royalty, Some(3), 0);
+    let res = create_collection(
+        &marketplace,
+        owner,
+        name_simple_nft,
+        init_nft_payload.encode(),
+    );
+    assert!(!res.main_failed());
+    let StateReply::AllCollections(collections) = marketplace
+        .read_state(StateQuery::AllCollections)
+        .expect("Unexpected invalid state.")
+    else {
+        panic!("Unexpected StateReply variant");
+    };
+    collections[0].0
+}
+
+/// Mints the next token of the collection to the user and approves it to the marketplace
+pub fn mint_approved_token(sys: &System, collection_address: ActorId, user: u64, token_id: u64) {
+    let address_nft: [u8; 32] = collection_address.into();
+    let nft_collection = sys.get_program(address_nft);
+    let res = nft_collection.send(user, NftAction::Mint);
+    assert!(!res.main_failed());
+    let address_marketplace: [u8; 32] = sys.get_program(1).id().into();
+    let res = nft_collection.send(
+        user,
+        NftAction::Approve {
+            to: address_marketplace.into(),
+            token_id,
+        },
+    );
+    assert!(!res.main_failed());
+}
+
 pub fn add_new_collection(
     marketplace: &Program,
     admin: u64,
@@ -58,6 +110,23 @@ pub fn create_collection(
     )
 }
 
+pub fn register_external_collection(
+    marketplace: &Program,
+    user: u64,
+    address: ActorId,
+    type_name: String,
+    token_id: u64,
+) -> RunResult {
+    marketplace.send(
+        user,
+        NftMarketplaceAction::RegisterExternalCollection {
+            address,
+            type_name,
+            token_id,
+        },
+    )
+}
+
 pub fn sale(
     marketplace: &Program,
     user: u64,
@@ -132,6 +201,8 @@ pub fn add_bid(
         NftMarketplaceAction::AddBid {
             collection_address,
             token_id,
+            recipient: None,
+            referrer: None,
         },
         price,
     )
@@ -148,6 +219,8 @@ pub fn buy(
         NftMarketplaceAction::BuyNft {
             collection_address,
             token_id,
+            recipient: None,
+            referrer: None,
         },
         price,
     )
@@ -159,12 +232,16 @@ pub fn create_offer(
     collection_address: ActorId,
     token_id: u64,
     value: u128,
+    expires_at: Option<u64>,
 ) -> RunResult {
     marketplace.send_with_value(
         user,
         NftMarketplaceAction::CreateOffer {
             collection_address,
             token_id,
+            expires_at,
+            recipient: None,
+            referrer: None,
         },
         value,
     )
@@ -185,6 +262,217 @@ pub fn accept_offer(
     marketplace.send(user, NftMarketplaceAction::AcceptOffer { offer })
 }
 
+pub fn counter_offer(marketplace: &Program, user: u64, offer: Offer, price: u128) -> RunResult {
+    marketplace.send(user, NftMarketplaceAction::CounterOffer { offer, price })
+}
+
+pub fn accept_counter_offer(
+    marketplace: &Program,
+    user: u64,
+    offer: Offer,
+    value: u128,
+) -> RunResult {
+    marketplace.send_with_value(user, NftMarketplaceAction::AcceptCounterOffer { offer }, value)
+}
+
+pub fn create_collection_offer(
+    marketplace: &Program,
+    user: u64,
+    collection_address: ActorId,
+    quantity: u64,
+    value: u128,
+) -> RunResult {
+    marketplace.send_with_value(
+        user,
+        NftMarketplaceAction::CreateCollectionOffer {
+            collection_address,
+            quantity,
+        },
+        value,
+    )
+}
+
+pub fn accept_collection_offer(
+    marketplace: &Program,
+    user: u64,
+    collection_address: ActorId,
+    creator: ActorId,
+    token_id: u64,
+) -> RunResult {
+    let offer = CollectionOffer {
+        collection_address,
+        creator,
+    };
+    marketplace.send(
+        user,
+        NftMarketplaceAction::AcceptCollectionOffer { offer, token_id },
+    )
+}
+
+pub fn create_trait_offer(
+    marketplace: &Program,
+    user: u64,
+    collection_address: ActorId,
+    layer: u32,
+    option: u8,
+    value: u128,
+) -> RunResult {
+    marketplace.send_with_value(
+        user,
+        NftMarketplaceAction::CreateTraitOffer {
+            collection_address,
+            layer,
+            option,
+        },
+        value,
+    )
+}
+
+pub fn accept_trait_offer(
+    marketplace: &Program,
+    user: u64,
+    offer: TraitOffer,
+    token_id: u64,
+) -> RunResult {
+    marketplace.send(
+        user,
+        NftMarketplaceAction::AcceptTraitOffer { offer, token_id },
+    )
+}
+
+pub fn propose_swap(
+    marketplace: &Program,
+    user: u64,
+    counterparty: ActorId,
+    proposer_items: Vec<(ActorId, u64)>,
+    counterparty_items: Vec<(ActorId, u64)>,
+    value: u128,
+) -> RunResult {
+    marketplace.send_with_value(
+        user,
+        NftMarketplaceAction::ProposeSwap {
+            counterparty,
+            proposer_items,
+            counterparty_items,
+            expires_at: None,
+        },
+        value,
+    )
+}
+
+pub fn accept_swap(marketplace: &Program, user: u64, swap_id: u64) -> RunResult {
+    marketplace.send(user, NftMarketplaceAction::AcceptSwap { swap_id })
+}
+
+pub fn list_for_rent(
+    marketplace: &Program,
+    user: u64,
+    collection_address: ActorId,
+    token_id: u64,
+    price_per_day: u128,
+    max_days: u32,
+) -> RunResult {
+    marketplace.send(
+        user,
+        NftMarketplaceAction::ListForRent {
+            collection_address,
+            token_id,
+            price_per_day,
+            max_days,
+        },
+    )
+}
+
+pub fn rent(
+    marketplace: &Program,
+    user: u64,
+    collection_address: ActorId,
+    token_id: u64,
+    days: u32,
+    value: u128,
+) -> RunResult {
+    marketplace.send_with_value(
+        user,
+        NftMarketplaceAction::Rent {
+            collection_address,
+            token_id,
+            days,
+        },
+        value,
+    )
+}
+
+pub fn request_loan(
+    marketplace: &Program,
+    user: u64,
+    collection_address: ActorId,
+    token_id: u64,
+    amount: u128,
+    interest: u128,
+    duration_ms: u64,
+) -> RunResult {
+    marketplace.send(
+        user,
+        NftMarketplaceAction::RequestLoan {
+            collection_address,
+            token_id,
+            amount,
+            interest,
+            duration_ms,
+        },
+    )
+}
+
+pub fn fund_loan(marketplace: &Program, user: u64, loan_id: u64, value: u128) -> RunResult {
+    marketplace.send_with_value(user, NftMarketplaceAction::FundLoan { loan_id }, value)
+}
+
+pub fn repay_loan(marketplace: &Program, user: u64, loan_id: u64, value: u128) -> RunResult {
+    marketplace.send_with_value(user, NftMarketplaceAction::RepayLoan { loan_id }, value)
+}
+
+pub fn create_raffle(
+    marketplace: &Program,
+    user: u64,
+    collection_address: ActorId,
+    token_id: u64,
+    ticket_price: u128,
+    max_tickets: u32,
+    min_tickets: u32,
+    ends_at: u64,
+) -> RunResult {
+    marketplace.send(
+        user,
+        NftMarketplaceAction::CreateRaffle {
+            collection_address,
+            token_id,
+            ticket_price,
+            max_tickets,
+            min_tickets,
+            ends_at,
+        },
+    )
+}
+
+pub fn buy_tickets(
+    marketplace: &Program,
+    user: u64,
+    collection_address: ActorId,
+    token_id: u64,
+    count: u32,
+    value: u128,
+) -> RunResult {
+    marketplace.send_with_value(
+        user,
+        NftMarketplaceAction::BuyTickets {
+            collection_address,
+            token_id,
+            count,
+        },
+        value,
+    )
+}
+
 pub fn add_admin(marketplace: &Program, admin: u64, users: Vec<ActorId>) -> RunResult {
     marketplace.send(admin, NftMarketplaceAction::AddAdmins { users })
 }
@@ -197,6 +485,7 @@ pub fn update_config(
     gas_for_close_auction: Option<u64>,
     gas_for_delete_collection: Option<u64>,
     gas_for_get_token_info: Option<u64>,
+    gas_for_cleanup_offers: Option<u64>,
     time_between_create_collections: Option<u64>,
     minimum_transfer_value: Option<u128>,
     ms_in_block: Option<u32>,
@@ -209,6 +498,7 @@ pub fn update_config(
             gas_for_close_auction,
             gas_for_delete_collection,
             gas_for_get_token_info,
+            gas_for_cleanup_offers,
             time_between_create_collections,
             minimum_transfer_value,
             ms_in_block,
@@ -260,6 +550,7 @@ pub fn get_init_nft_payload(
             collection_tags: vec!["tag1".to_string()],
             additional_links: None,
             royalty,
+            royalty_recipients: vec![],
             user_mint_limit,
             payment_for_mint,
             transferable: Some(0),
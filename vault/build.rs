@@ -0,0 +1,5 @@
+use vault_io::VaultMetadata;
+
+fn main() {
+    gear_wasm_builder::build_with_metadata::<VaultMetadata>();
+}
@@ -0,0 +1,140 @@
+#![no_std]
+
+use gmeta::{In, InOut, Metadata};
+use gstd::{prelude::*, ActorId};
+
+pub struct VaultMetadata;
+
+impl Metadata for VaultMetadata {
+    type Init = In<VaultInit>;
+    type Handle = InOut<VaultAction, Result<VaultEvent, VaultError>>;
+    type Others = ();
+    type Reply = ();
+    type Signal = ();
+    type State = InOut<StateQuery, StateReply>;
+}
+
+/// * marketplace - address of the marketplace that created the vault
+/// * depositor - owner of the token who receives all the shares
+/// * collection_address, token_id - the token kept by the vault
+/// * total_shares - number of shares minted to the depositor
+/// * reserve_price - minimum price at which the buyout auction can be started
+/// * buyout_duration_ms - duration of the buyout auction
+/// * gas_for_transfer_token - gas that is needed to transfer the token
+/// * gas_for_listing - gas that is needed by the marketplace to list the token
+/// * gas_for_close_buyout - gas which is needed to send a delayed message to close the buyout auction
+/// * ms_in_block - number of milliseconds in one block
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+pub struct VaultInit {
+    pub marketplace: ActorId,
+    pub depositor: ActorId,
+    pub collection_address: ActorId,
+    pub token_id: u64,
+    pub total_shares: u128,
+    pub reserve_price: u128,
+    pub buyout_duration_ms: u64,
+    pub gas_for_transfer_token: u64,
+    pub gas_for_listing: u64,
+    pub gas_for_close_buyout: u64,
+    pub ms_in_block: u32,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+pub enum VaultAction {
+    TransferShares { to: ActorId, amount: u128 },
+    StartBuyout,
+    BidBuyout,
+    EndBuyout,
+    Redeem,
+    VoteForListing { listing: Listing },
+    SyncStatus,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+pub enum VaultEvent {
+    SharesTransferred {
+        from: ActorId,
+        to: ActorId,
+        amount: u128,
+    },
+    BuyoutStarted {
+        bidder: ActorId,
+        price: u128,
+        ends_at: u64,
+    },
+    BuyoutBidAdded {
+        bidder: ActorId,
+        price: u128,
+    },
+    BuyoutEnded {
+        winner: ActorId,
+        price: u128,
+    },
+    Redeemed {
+        holder: ActorId,
+        shares: u128,
+        value: u128,
+    },
+    Voted {
+        holder: ActorId,
+        listing: Listing,
+    },
+    Listed {
+        listing: Listing,
+    },
+    StatusSynced {
+        status: VaultStatus,
+    },
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+pub struct VaultError(pub String);
+
+/// The way the share holders want to sell the token through the marketplace
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+pub enum Listing {
+    Sale { price: u128 },
+    Auction { min_price: u128, duration_ms: u32 },
+}
+
+/// * Active - the token is kept by the vault
+/// * Buyout - the buyout auction is in progress
+/// * Listed - the token is on sale or auction in the marketplace
+/// * Sold - the token is sold, share holders can redeem the proceeds
+#[derive(Debug, Clone, Encode, Decode, TypeInfo, PartialEq, Eq)]
+pub enum VaultStatus {
+    Active,
+    Buyout {
+        bidder: ActorId,
+        price: u128,
+        ends_at: u64,
+    },
+    Listed(Listing),
+    Sold,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+pub struct VaultState {
+    pub marketplace: ActorId,
+    pub collection_address: ActorId,
+    pub token_id: u64,
+    pub total_shares: u128,
+    pub shares: Vec<(ActorId, u128)>,
+    pub reserve_price: u128,
+    pub buyout_duration_ms: u64,
+    pub status: VaultStatus,
+    pub votes: Vec<(ActorId, Listing)>,
+    pub proceeds: u128,
+}
+
+#[derive(Encode, Decode, TypeInfo)]
+pub enum StateQuery {
+    All,
+    Shares(ActorId),
+}
+
+#[derive(Encode, Decode, TypeInfo)]
+pub enum StateReply {
+    All(VaultState),
+    Shares(u128),
+}
@@ -0,0 +1,424 @@
+#![no_std]
+use gstd::{collections::HashMap, exec, msg, prelude::*, ActorId};
+use nft_marketplace_io::{
+    NftAction, NftError, NftEvent, NftMarketplaceAction, NftMarketplaceError, NftMarketplaceEvent,
+};
+use vault_io::*;
+
+#[derive(Debug)]
+struct Vault {
+    pub marketplace: ActorId,
+    pub collection_address: ActorId,
+    pub token_id: u64,
+    pub total_shares: u128,
+    pub shares: HashMap<ActorId, u128>,
+    pub reserve_price: u128,
+    pub buyout_duration_ms: u64,
+    pub status: VaultStatus,
+    pub votes: HashMap<ActorId, Listing>,
+    pub proceeds: u128,
+    pub gas_for_transfer_token: u64,
+    pub gas_for_listing: u64,
+    pub gas_for_close_buyout: u64,
+    pub ms_in_block: u32,
+}
+static mut VAULT: Option<Vault> = None;
+
+impl Vault {
+    fn transfer_shares(&mut self, to: ActorId, amount: u128) -> Result<VaultEvent, VaultError> {
+        let from = msg::source();
+        let balance = self.shares.get(&from).copied().unwrap_or_default();
+        if amount == 0 || amount > balance {
+            return Err(VaultError("Insufficient shares".to_owned()));
+        }
+        if balance == amount {
+            self.shares.remove(&from);
+            self.votes.remove(&from);
+        } else {
+            self.shares.insert(from, balance - amount);
+        }
+        *self.shares.entry(to).or_default() += amount;
+
+        Ok(VaultEvent::SharesTransferred { from, to, amount })
+    }
+
+    fn start_buyout(&mut self) -> Result<VaultEvent, VaultError> {
+        let price = msg::value();
+        let bidder = msg::source();
+        if self.status != VaultStatus::Active {
+            return Err(VaultError(
+                "The buyout can only be started while the token is in the vault".to_owned(),
+            ));
+        }
+        if price < self.reserve_price {
+            return Err(VaultError(format!(
+                "The price must be at least the reserve price ({})",
+                self.reserve_price
+            )));
+        }
+
+        let ends_at = exec::block_timestamp() + self.buyout_duration_ms;
+        self.status = VaultStatus::Buyout {
+            bidder,
+            price,
+            ends_at,
+        };
+        // the delayed message closes the buyout auction
+        let delay = self.buyout_duration_ms / self.ms_in_block as u64 + 1;
+        msg::send_with_gas_delayed(
+            exec::program_id(),
+            VaultAction::EndBuyout,
+            self.gas_for_close_buyout,
+            0,
+            delay.try_into().unwrap_or(u32::MAX),
+        )
+        .expect("Error in sending delayed message");
+
+        Ok(VaultEvent::BuyoutStarted {
+            bidder,
+            price,
+            ends_at,
+        })
+    }
+
+    fn bid_buyout(&mut self) -> Result<VaultEvent, VaultError> {
+        let price = msg::value();
+        let bidder = msg::source();
+        let VaultStatus::Buyout {
+            bidder: current_bidder,
+            price: current_price,
+            ends_at,
+        } = self.status
+        else {
+            return Err(VaultError("There is no buyout auction".to_owned()));
+        };
+        if ends_at <= exec::block_timestamp() {
+            return Err(VaultError("The buyout auction has ended".to_owned()));
+        }
+        if price <= current_price {
+            return Err(VaultError(
+                "The bid must be greater than the current price".to_owned(),
+            ));
+        }
+
+        // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+        msg::send_with_gas(current_bidder, "", 0, current_price).expect("Error in sending value");
+        self.status = VaultStatus::Buyout {
+            bidder,
+            price,
+            ends_at,
+        };
+
+        Ok(VaultEvent::BuyoutBidAdded { bidder, price })
+    }
+
+    async fn end_buyout(&mut self) -> Result<VaultEvent, VaultError> {
+        let VaultStatus::Buyout {
+            bidder,
+            price,
+            ends_at,
+        } = self.status
+        else {
+            return Err(VaultError("There is no buyout auction".to_owned()));
+        };
+        if ends_at > exec::block_timestamp() {
+            return Err(VaultError(
+                "The buyout auction has not ended yet".to_owned(),
+            ));
+        }
+
+        self.transfer_token(bidder, self.gas_for_transfer_token)
+            .await?;
+        self.status = VaultStatus::Sold;
+        self.proceeds = price;
+        self.votes.clear();
+        self.close();
+
+        Ok(VaultEvent::BuyoutEnded {
+            winner: bidder,
+            price,
+        })
+    }
+
+    fn redeem(&mut self) -> Result<VaultEvent, VaultError> {
+        let holder = msg::source();
+        if self.status != VaultStatus::Sold {
+            return Err(VaultError("The token has not been sold yet".to_owned()));
+        }
+        let Some(shares) = self.shares.remove(&holder) else {
+            return Err(VaultError("You have no shares".to_owned()));
+        };
+
+        // the proceeds are distributed pro rata to the shares that have not yet been redeemed
+        let value = self.proceeds * shares / self.total_shares;
+        self.proceeds -= value;
+        self.total_shares -= shares;
+        if value != 0 {
+            // use send_with_gas to transfer the value directly to the balance, not to the mailbox.
+            msg::send_with_gas(holder, "", 0, value).expect("Error in sending value");
+        }
+
+        Ok(VaultEvent::Redeemed {
+            holder,
+            shares,
+            value,
+        })
+    }
+
+    async fn vote_for_listing(&mut self, listing: Listing) -> Result<VaultEvent, VaultError> {
+        let holder = msg::source();
+        if self.status != VaultStatus::Active {
+            return Err(VaultError(
+                "The token can only be listed while it is in the vault".to_owned(),
+            ));
+        }
+        if !self.shares.contains_key(&holder) {
+            return Err(VaultError("You have no shares".to_owned()));
+        }
+        self.votes.insert(holder, listing.clone());
+
+        // the token is listed as soon as the holders of more than half of the shares agree
+        let votes_for: u128 = self
+            .votes
+            .iter()
+            .filter(|(_, vote)| **vote == listing)
+            .map(|(voter, _)| self.shares.get(voter).copied().unwrap_or_default())
+            .sum();
+        if votes_for <= self.total_shares - votes_for {
+            return Ok(VaultEvent::Voted { holder, listing });
+        }
+
+        self.status = VaultStatus::Listed(listing.clone());
+        if let Err(error) = self.list_token(&listing).await {
+            self.status = VaultStatus::Active;
+            return Err(error);
+        }
+        self.votes.clear();
+
+        Ok(VaultEvent::Listed { listing })
+    }
+
+    /// The token is transferred to the marketplace before listing,
+    /// the marketplace returns it to the vault if the listing fails
+    async fn list_token(&self, listing: &Listing) -> Result<(), VaultError> {
+        self.transfer_token(self.marketplace, self.gas_for_transfer_token)
+            .await?;
+
+        let payload = match *listing {
+            Listing::Sale { price } => NftMarketplaceAction::SaleNft {
+                collection_address: self.collection_address,
+                token_id: self.token_id,
+                price,
+            },
+            Listing::Auction {
+                min_price,
+                duration_ms,
+            } => NftMarketplaceAction::CreateAuction {
+                collection_address: self.collection_address,
+                token_id: self.token_id,
+                min_price,
+                duration_ms,
+            },
+        };
+        let reply = msg::send_with_gas_for_reply_as::<
+            NftMarketplaceAction,
+            Result<NftMarketplaceEvent, NftMarketplaceError>,
+        >(self.marketplace, payload, self.gas_for_listing, 0, 0)
+        .expect("Error during sending a listing to the marketplace")
+        .await
+        .expect("Problem with listing");
+
+        match reply {
+            Ok(_) => Ok(()),
+            Err(NftMarketplaceError(error_string)) => Err(VaultError(error_string)),
+        }
+    }
+
+    /// The token comes back to the vault if the marketplace listing is canceled
+    /// or the auction ends without bids
+    async fn sync_status(&mut self) -> Result<VaultEvent, VaultError> {
+        if let VaultStatus::Listed(_) = self.status {
+            let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+                self.collection_address,
+                NftAction::GetTokenInfo {
+                    token_id: self.token_id,
+                },
+                self.gas_for_transfer_token,
+                0,
+                0,
+            )
+            .expect("Error during `NftAction::GetTokenInfo`")
+            .await
+            .expect("Problem with get token info");
+
+            match reply {
+                Ok(NftEvent::TokenInfoReceived { token_owner, .. })
+                    if token_owner == exec::program_id() =>
+                {
+                    self.status = VaultStatus::Active;
+                }
+                Err(NftError(error_string)) => return Err(VaultError(error_string)),
+                _ => {}
+            }
+        }
+
+        Ok(VaultEvent::StatusSynced {
+            status: self.status.clone(),
+        })
+    }
+
+    /// The marketplace pays the proceeds with a message without payload
+    fn receive_proceeds(&mut self) {
+        if msg::source() == self.marketplace {
+            if let VaultStatus::Listed(_) = self.status {
+                self.status = VaultStatus::Sold;
+                self.votes.clear();
+                self.close();
+            }
+            if self.status == VaultStatus::Sold {
+                self.proceeds += msg::value();
+            }
+        }
+    }
+
+    /// The sold token is no longer in the vault, so the marketplace forgets the vault
+    fn close(&self) {
+        msg::send(self.marketplace, NftMarketplaceAction::CloseVault, 0)
+            .expect("Error in sending a message to the marketplace");
+    }
+
+    async fn transfer_token(&self, to: ActorId, gas: u64) -> Result<(), VaultError> {
+        let reply = msg::send_with_gas_for_reply_as::<NftAction, Result<NftEvent, NftError>>(
+            self.collection_address,
+            NftAction::Transfer {
+                to,
+                token_id: self.token_id,
+            },
+            gas,
+            0,
+            0,
+        )
+        .expect("Error during send message `NftAction::Transfer`")
+        .await
+        .expect("Program was problem with transfer");
+
+        match reply {
+            Ok(_) => Ok(()),
+            Err(NftError(error_string)) => Err(VaultError(error_string)),
+        }
+    }
+}
+
+#[no_mangle]
+extern "C" fn init() {
+    let VaultInit {
+        marketplace,
+        depositor,
+        collection_address,
+        token_id,
+        total_shares,
+        reserve_price,
+        buyout_duration_ms,
+        gas_for_transfer_token,
+        gas_for_listing,
+        gas_for_close_buyout,
+        ms_in_block,
+    } = msg::load().expect("Unable to decode `VaultInit`");
+
+    assert!(
+        total_shares > 0,
+        "The number of shares must be greater than zero"
+    );
+
+    unsafe {
+        VAULT = Some(Vault {
+            marketplace,
+            collection_address,
+            token_id,
+            total_shares,
+            shares: HashMap::from([(depositor, total_shares)]),
+            reserve_price,
+            buyout_duration_ms,
+            status: VaultStatus::Active,
+            votes: HashMap::new(),
+            proceeds: 0,
+            gas_for_transfer_token,
+            gas_for_listing,
+            gas_for_close_buyout,
+            ms_in_block,
+        })
+    };
+}
+
+#[gstd::async_main]
+async fn main() {
+    let vault = unsafe { VAULT.as_mut().expect("`Vault` is not initialized.") };
+    if msg::size() == 0 {
+        vault.receive_proceeds();
+        return;
+    }
+    let action: VaultAction = msg::load().expect("Unable to decode `VaultAction`");
+    let result = match action {
+        VaultAction::TransferShares { to, amount } => vault.transfer_shares(to, amount),
+        VaultAction::StartBuyout => vault.start_buyout(),
+        VaultAction::BidBuyout => vault.bid_buyout(),
+        VaultAction::EndBuyout => vault.end_buyout().await,
+        VaultAction::Redeem => vault.redeem(),
+        VaultAction::VoteForListing { listing } => vault.vote_for_listing(listing).await,
+        VaultAction::SyncStatus => vault.sync_status().await,
+    };
+
+    msg::reply(result, 0)
+        .expect("Failed to encode or reply with `Result<VaultEvent, VaultError>`.");
+}
+
+#[no_mangle]
+extern "C" fn state() {
+    let vault = unsafe {
+        VAULT
+            .take()
+            .expect("Unexpected: The contract is not initialized")
+    };
+    let query: StateQuery = msg::load().expect("Unable to load the state query");
+    let reply = match query {
+        StateQuery::All => StateReply::All(vault.into()),
+        StateQuery::Shares(holder) => {
+            StateReply::Shares(vault.shares.get(&holder).copied().unwrap_or_default())
+        }
+    };
+    msg::reply(reply, 0).expect("Unable to share state");
+}
+
+impl From<Vault> for VaultState {
+    fn from(value: Vault) -> Self {
+        let Vault {
+            marketplace,
+            collection_address,
+            token_id,
+            total_shares,
+            shares,
+            reserve_price,
+            buyout_duration_ms,
+            status,
+            votes,
+            proceeds,
+            ..
+        } = value;
+
+        let shares = shares.into_iter().collect();
+        let votes = votes.into_iter().collect();
+
+        Self {
+            marketplace,
+            collection_address,
+            token_id,
+            total_shares,
+            shares,
+            reserve_price,
+            buyout_duration_ms,
+            status,
+            votes,
+            proceeds,
+        }
+    }
+}